@@ -1,6 +1,10 @@
 use criterion::{criterion_group, criterion_main};
 
+#[cfg(not(feature = "bench"))]
 criterion_group!(benches, sequence::sequence_bench);
+// the residue benches need the internal moduli, with `cargo bench --features bench`
+#[cfg(feature = "bench")]
+criterion_group!(benches, sequence::sequence_bench, residue::residue_bench);
 criterion_main!(benches);
 
 mod sequence {
@@ -14,6 +18,13 @@ mod sequence {
         group.bench_function("n_u16", bench_n_u16);
         group.bench_function("n_u32", bench_n_u32);
         group.bench_function("n_u64", bench_n_u64);
+        group.bench_function("n_u128", bench_n_u128);
+        group.bench_function("n_loop_u32", bench_n_loop_u32);
+        group.bench_function("n_loop_u64", bench_n_loop_u64);
+        group.bench_function("n_loop_u128", bench_n_loop_u128);
+        group.bench_function("fill_u32", bench_fill_u32);
+        group.bench_function("fill_u64", bench_fill_u64);
+        group.bench_function("fill_u128", bench_fill_u128);
         group.bench_function("rand_u64", bench_rand_u64);
     }
 
@@ -35,9 +46,90 @@ mod sequence {
     bench_n!(bench_n_u16, u16);
     bench_n!(bench_n_u32, u32);
     bench_n!(bench_n_u64, u64);
+    bench_n!(bench_n_u128, u128);
+
+    macro_rules! bench_n_loop {
+        ($name:ident, $type:ident) => {
+            /// Compute 1024 outputs by calling n() in a loop, the baseline for fill().
+            fn $name(b: &mut Bencher) {
+                let sequence = RandomSequence::<$type>::rand(&mut rand::rng());
+                let mut out = [0 as $type; 1024];
+
+                b.iter_batched(
+                    || sequence.clone(),
+                    |sequence| {
+                        for (index, value) in out.iter_mut().enumerate() {
+                            *value = sequence.n(index as $type);
+                        }
+                        black_box(&out);
+                    },
+                    BatchSize::SmallInput,
+                );
+            }
+        };
+    }
+
+    bench_n_loop!(bench_n_loop_u32, u32);
+    bench_n_loop!(bench_n_loop_u64, u64);
+    bench_n_loop!(bench_n_loop_u128, u128);
+
+    macro_rules! bench_fill {
+        ($name:ident, $type:ident) => {
+            /// Fill a slice of 1024 outputs, to compare the amortised time per output with n().
+            fn $name(b: &mut Bencher) {
+                let sequence = RandomSequence::<$type>::rand(&mut rand::rng());
+                let mut out = [0 as $type; 1024];
+
+                b.iter_batched(
+                    || sequence.clone(),
+                    |mut sequence| black_box(sequence.fill(&mut out)),
+                    BatchSize::SmallInput,
+                );
+            }
+        };
+    }
+
+    bench_fill!(bench_fill_u32, u32);
+    bench_fill!(bench_fill_u64, u64);
+    bench_fill!(bench_fill_u128, u128);
 
     /// Compare standard random number generation time.
     fn bench_rand_u64(b: &mut Bencher) {
         b.iter(|| black_box(rand::random::<u64>()))
     }
 }
+
+#[cfg(feature = "bench")]
+mod residue {
+    use std::hint::black_box;
+    use criterion::{BatchSize, Bencher, Criterion};
+    use rand_unique::residue::{Division, Montgomery};
+
+    pub fn residue_bench(c: &mut Criterion) {
+        let mut group = c.benchmark_group("residue");
+        group.bench_function("division_u32", bench_division_u32);
+        group.bench_function("montgomery_u32", bench_montgomery_u32);
+        group.bench_function("division_u64", bench_division_u64);
+        group.bench_function("montgomery_u64", bench_montgomery_u64);
+    }
+
+    macro_rules! bench_residue {
+        ($name:ident, $type:ident, $modulus:ty, $prime:expr) => {
+            /// Square a random value below the prime, as the sequence does for each residue.
+            fn $name(b: &mut Bencher) {
+                let modulus = <$modulus>::new($prime);
+
+                b.iter_batched(
+                    || rand::random::<$type>() % $prime,
+                    |x| black_box(modulus.mul_mod(x, x)),
+                    BatchSize::SmallInput,
+                );
+            }
+        };
+    }
+
+    bench_residue!(bench_division_u32, u32, Division<u32>, 4294967291);
+    bench_residue!(bench_montgomery_u32, u32, Montgomery<u32>, 4294967291);
+    bench_residue!(bench_division_u64, u64, Division<u64>, 18446744073709551427);
+    bench_residue!(bench_montgomery_u64, u64, Montgomery<u64>, 18446744073709551427);
+}
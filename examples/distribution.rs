@@ -41,7 +41,7 @@ fn plot_histogram(file_name: &str, bins: u32, count: u32) -> Result<(), Box<dyn
 
     chart.draw_series(Histogram::vertical(&chart).style(RED.filled()).data(binned))?;
 
-    root.present().expect(&format!("Unable to write result to file {file_name}"));
+    root.present().unwrap_or_else(|_| panic!("Unable to write result to file {file_name}"));
     println!("Histogram has been saved to {file_name}");
 
     Ok(())
@@ -73,9 +73,9 @@ fn plot_scatter(file_name: &str, count: u32) -> Result<(), Box<dyn Error>> {
         .draw()
         .unwrap();
 
-    chart.draw_series(data.iter().map(|point| Circle::new(*point, 2, &BLUE)))?;
+    chart.draw_series(data.iter().map(|point| Circle::new(*point, 2, BLUE)))?;
 
-    root.present().expect(&format!("Unable to write result to file {file_name}"));
+    root.present().unwrap_or_else(|_| panic!("Unable to write result to file {file_name}"));
     println!("Scatter has been saved to {file_name}");
 
     Ok(())
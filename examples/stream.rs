@@ -0,0 +1,43 @@
+use std::error::Error;
+use std::io::{self, ErrorKind, Write};
+
+use rand_unique::io::SequenceReader;
+use rand_unique::RandomSequence;
+
+/// Write the raw bytes of a sequence to stdout, to pipe into external random number test batteries.
+///
+/// ```sh
+/// cargo run --release --example stream --features std -- u64 | RNG_test stdin64
+/// cargo run --release --example stream --features std -- u32 1 2 | dieharder -a -g 200
+/// ```
+///
+/// The arguments are the type, `u32` or `u64`, then the optional `seed_base` and `seed_offset`.
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let seed = |i: usize| args.get(i).map_or(Ok(0), |seed| seed.parse::<u64>());
+    let (seed_base, seed_offset) = (seed(1)?, seed(2)?);
+
+    let stdout = io::stdout().lock();
+    let result = match args.first().map(String::as_str) {
+        Some("u32") => {
+            let sequence = RandomSequence::<u32>::new(seed_base as u32, seed_offset as u32);
+            copy(SequenceReader::new(sequence), stdout)
+        }
+        Some("u64") | None => {
+            let sequence = RandomSequence::<u64>::new(seed_base, seed_offset);
+            copy(SequenceReader::new(sequence), stdout)
+        }
+        Some(other) => return Err(format!("unsupported type {}, expected u32 or u64", other).into()),
+    };
+
+    // the test battery closes the pipe once it has read enough
+    match result {
+        Err(error) if error.kind() == ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+fn copy(mut reader: impl io::Read, mut writer: impl Write) -> io::Result<()> {
+    io::copy(&mut reader, &mut writer)?;
+    writer.flush()
+}
@@ -0,0 +1,651 @@
+//! Statistical quality metrics for a [RandomSequence], which measure how closely the sequence
+//! resembles a uniformly random permutation.
+//!
+//! [analyze] runs every test over the first indices of a sequence and returns a [Report], with a p
+//! value for each test. The sequence is deterministic, and so is the report, which makes it usable
+//! in tests to catch regressions when the constants or the permutation change:
+//!
+//! ```
+//! use rand_unique::RandomSequenceBuilder;
+//! use rand_unique::analysis::analyze;
+//!
+//! let sequence = RandomSequenceBuilder::<u32>::new(0, 0).into_iter();
+//! let report = analyze(&sequence, 10_000);
+//! assert!(report.passed(0.001), "{:?}", report);
+//! ```
+//!
+//! Each p value is the probability of a result at least as extreme from a uniformly random
+//! permutation, so a test fails by chance with probability `alpha` for a good sequence.
+
+use std::vec;
+
+use num_traits::{NumCast, One, PrimInt, ToPrimitive, WrappingAdd, WrappingSub, Zero};
+
+use crate::builder::SequenceInt;
+use crate::permutation::Permutation;
+use crate::sequence::RandomSequence;
+
+/// The smallest number of samples that [analyze] accepts.
+const MIN_SAMPLES: usize = 32;
+
+/// The largest number of buckets in the uniformity test.
+const MAX_BUCKETS: u128 = 1024;
+
+/// The number of indices in the avalanche test, each flipped at every bit.
+const AVALANCHE_SAMPLES: usize = 1024;
+
+/// The largest number of gap lengths in the gap test, before the tail.
+const MAX_GAP_CLASSES: usize = 16;
+
+/// The smallest expected count of each class in a chi-squared test.
+const MIN_EXPECTED: f64 = 5.0;
+
+/// The result of a chi-squared goodness of fit test against the distribution of a uniformly random
+/// permutation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChiSquaredTest {
+    /// The chi-squared statistic.
+    pub statistic: f64,
+
+    /// The degrees of freedom, one less than the number of classes.
+    pub degrees_of_freedom: usize,
+
+    /// The probability of a statistic at least this large from a uniformly random permutation.
+    pub p_value: f64,
+}
+
+/// The result of a test for a linear correlation between two series of outputs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CorrelationTest {
+    /// The Pearson correlation coefficient, in `[-1, 1]` and ideally close to 0.
+    pub coefficient: f64,
+
+    /// The probability of a correlation at least this strong between independent series.
+    pub p_value: f64,
+}
+
+/// The result of the avalanche test, which flips each bit of an index and counts the output bits
+/// that change.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AvalancheTest {
+    /// The mean fraction of output bits which flip when a single index bit is flipped, ideally 0.5
+    /// for power of two domains.
+    pub mean: f64,
+
+    /// The largest deviation of the flip rate of any pair of index bit and output bit from its
+    /// expected rate, which is 0.5 for power of two domains.
+    pub worst_bias: f64,
+
+    /// The probability of a deviation at least this large from a uniformly random permutation,
+    /// corrected for the number of bit pairs.
+    pub p_value: f64,
+}
+
+/// The results of every test from [analyze].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Report {
+    /// The number of indices sampled, which is capped to the length of the sequence.
+    pub samples: usize,
+
+    /// The chi-squared test of the outputs falling into equal width buckets over the domain, with
+    /// the exact expected count of each bucket.
+    pub uniformity: ChiSquaredTest,
+
+    /// The correlation between consecutive outputs `n(i)` and `n(i + 1)`.
+    pub serial_correlation: CorrelationTest,
+
+    /// The avalanche test of single bit flips of the index.
+    pub avalanche: AvalancheTest,
+
+    /// The chi-squared test of the gap lengths between outputs in the lower half of the domain,
+    /// which are geometrically distributed for a random sequence.
+    pub gaps: ChiSquaredTest,
+
+    /// The strongest correlation between `n(i)` of this sequence and of the sequences with
+    /// `seed_base + 1` or `seed_offset + 1`, corrected for the two comparisons.
+    pub seed_correlation: CorrelationTest,
+}
+
+impl Report {
+    /// The smallest p value of every test.
+    #[inline]
+    pub fn min_p_value(&self) -> f64 {
+        [
+            self.uniformity.p_value,
+            self.serial_correlation.p_value,
+            self.avalanche.p_value,
+            self.gaps.p_value,
+            self.seed_correlation.p_value,
+        ]
+        .into_iter()
+        .fold(1.0, f64::min)
+    }
+
+    /// Check every test passes at the significance level `alpha`, such as `0.001`.
+    #[inline]
+    pub fn passed(&self, alpha: f64) -> bool {
+        self.min_p_value() >= alpha
+    }
+}
+
+/// Run every test over the first `samples` indices of the whole sequence, ignoring its cursor and
+/// shards. The samples are capped to the length of the sequence, and the avalanche test uses the
+/// first 1024 indices.
+///
+/// The sequences with nearby seeds are built from the config with a clone of the permutation, using
+/// [RandomSequenceBuilder::into_iter_with](crate::RandomSequenceBuilder::into_iter_with).
+///
+/// # Panics
+///
+/// Panics if there are fewer than 32 samples, or the sequence has fewer than 32 values.
+pub fn analyze<T, P>(sequence: &RandomSequence<T, P>, samples: usize) -> Report
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+    let domain = Domain::new(sequence);
+    let samples = match domain.len {
+        Some(len) if len < samples as u128 => len as usize,
+        _ => samples,
+    };
+    assert!(samples >= MIN_SAMPLES, "analysis requires at least 32 samples");
+
+    let positions: vec::Vec<u128> = (0..samples).map(|i| domain.position(sequence.n_unsigned(index(i)))).collect();
+
+    Report {
+        samples,
+        uniformity: uniformity(&domain, &positions),
+        serial_correlation: correlation(
+            positions[..samples - 1].iter().map(|&p| domain.fraction(p)),
+            positions[1..].iter().map(|&p| domain.fraction(p)),
+        ),
+        avalanche: avalanche(sequence, &domain, samples.min(AVALANCHE_SAMPLES)),
+        gaps: gaps(&domain, &positions),
+        seed_correlation: seed_correlation(sequence, &domain, &positions),
+    }
+}
+
+/// The index `i` as the unsigned type of the sequence.
+#[inline]
+fn index<U: NumCast>(i: usize) -> U {
+    U::from(i).unwrap()
+}
+
+/// The domain of the sequence, `[min, min + len)`, where outputs are compared by their position
+/// `value - min`.
+struct Domain<T>
+where
+    T: SequenceInt,
+{
+    /// The number of values, `None` if it's the entire `u128`.
+    len: Option<u128>,
+
+    /// The number of bits in a position.
+    bits: u32,
+
+    /// The smallest value.
+    min: T::Unsigned,
+}
+
+impl<T> Domain<T>
+where
+    T: SequenceInt,
+{
+    fn new<P: Permutation<T>>(sequence: &RandomSequence<T, P>) -> Self {
+        let len = match sequence.config.len {
+            Some(len) => len.to_u128(),
+            None => 1u128.checked_shl(T::Unsigned::zero().count_zeros()),
+        };
+        let bits = match len {
+            Some(len) => 128 - (len - 1).leading_zeros(),
+            None => 128,
+        };
+        Self {
+            len,
+            bits,
+            min: sequence.config.min.to_unsigned(),
+        }
+    }
+
+    /// The number of values as a float.
+    #[inline]
+    fn size(&self) -> f64 {
+        self.len.map_or(2f64.powi(128), |len| len as f64)
+    }
+
+    /// The position of a value in the domain.
+    #[inline]
+    fn position(&self, value: T) -> u128 {
+        value.to_unsigned().wrapping_sub(&self.min).to_u128().unwrap()
+    }
+
+    /// The position as a fraction of the domain, in `[0, 1)`.
+    #[inline]
+    fn fraction(&self, position: u128) -> f64 {
+        position as f64 / self.size()
+    }
+
+    /// The number of positions with `bit` set.
+    #[inline]
+    fn ones(&self, bit: u32) -> f64 {
+        match self.len {
+            Some(len) => {
+                let period = 1u128 << bit;
+                let full = (len >> (bit + 1)) << bit;
+                (full + (len & ((period << 1) - 1)).saturating_sub(period)) as f64
+            }
+            None => 2f64.powi(127),
+        }
+    }
+}
+
+/// The chi-squared test of equal width buckets, with the exact expected counts of the possibly
+/// smaller final bucket, and corrected for sampling without replacement.
+fn uniformity<T>(domain: &Domain<T>, positions: &[u128]) -> ChiSquaredTest
+where
+    T: SequenceInt,
+{
+    let samples = positions.len() as f64;
+
+    // buckets of width ceil(len / buckets), where the final bucket holds the remainder
+    let target = (positions.len() as u128 / MIN_EXPECTED as u128).clamp(2, MAX_BUCKETS);
+    let max_position = domain.len.map_or(u128::MAX, |len| len - 1);
+    let width = max_position / target + 1;
+    let buckets = (max_position / width + 1) as usize;
+
+    let mut counts = vec![0usize; buckets];
+    for &position in positions {
+        counts[(position / width) as usize] += 1;
+    }
+
+    let last_width = max_position - (buckets as u128 - 1) * width + 1;
+    let statistic = counts
+        .iter()
+        .enumerate()
+        .map(|(bucket, &count)| {
+            let width = if bucket + 1 == buckets { last_width } else { width };
+            let expected = samples * (width as f64 / domain.size());
+            (count as f64 - expected).powi(2) / expected
+        })
+        .sum::<f64>();
+
+    // sampling without replacement reduces the variance of each count by (N - n) / (N - 1)
+    let statistic = match domain.size() - samples {
+        remaining if remaining >= 1.0 => statistic * (domain.size() - 1.0) / remaining,
+        _ => statistic,
+    };
+    chi_squared_test(statistic, buckets - 1)
+}
+
+/// The Pearson correlation of two series, tested with the Fisher transformation.
+fn correlation(xs: impl Iterator<Item = f64> + Clone, ys: impl Iterator<Item = f64> + Clone) -> CorrelationTest {
+    let n = xs.clone().count() as f64;
+    let mean_x = xs.clone().sum::<f64>() / n;
+    let mean_y = ys.clone().sum::<f64>() / n;
+
+    let (mut covariance, mut variance_x, mut variance_y) = (0.0, 0.0, 0.0);
+    for (x, y) in xs.zip(ys) {
+        covariance += (x - mean_x) * (y - mean_y);
+        variance_x += (x - mean_x).powi(2);
+        variance_y += (y - mean_y).powi(2);
+    }
+
+    let coefficient = match variance_x > 0.0 && variance_y > 0.0 {
+        true => (covariance / (variance_x * variance_y).sqrt()).clamp(-1.0, 1.0),
+        false => 0.0,
+    };
+    let z = coefficient.atanh() * (n - 3.0).sqrt();
+    CorrelationTest {
+        coefficient,
+        p_value: normal_test(z),
+    }
+}
+
+/// The avalanche test over the first `samples` indices, against the exact probability that two
+/// distinct random positions differ in each bit.
+fn avalanche<T, P>(sequence: &RandomSequence<T, P>, domain: &Domain<T>, samples: usize) -> AvalancheTest
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+    let bits = domain.bits as usize;
+    let max_index = domain.len.map_or(u128::MAX, |len| len - 1);
+
+    // flips[input * bits + output] counts the output bit flips for each flipped index bit
+    let mut flips = vec![0u64; bits * bits];
+    let mut trials = vec![0u64; bits];
+    for i in 0..samples {
+        let output = domain.position(sequence.n_unsigned(index(i)));
+        for input in 0..bits {
+            // count each pair once, from the index without the bit set
+            let flipped = i as u128 | (1 << input);
+            if flipped == i as u128 || flipped > max_index {
+                continue;
+            }
+            trials[input] += 1;
+
+            let mut diff = output ^ domain.position(sequence.n_unsigned(NumCast::from(flipped).unwrap()));
+            while diff != 0 {
+                flips[input * bits + diff.trailing_zeros() as usize] += 1;
+                diff &= diff - 1;
+            }
+        }
+    }
+
+    let size = domain.size();
+    let expected: vec::Vec<f64> = (0..domain.bits)
+        .map(|bit| {
+            let ones = domain.ones(bit);
+            2.0 * ones * (size - ones) / (size * (size - 1.0))
+        })
+        .collect();
+
+    let (mut total_flips, mut total_trials) = (0u64, 0u64);
+    let (mut worst_bias, mut worst_z, mut pairs) = (0f64, 0f64, 0usize);
+    for input in 0..bits {
+        let trials = trials[input];
+        if trials == 0 {
+            continue;
+        }
+        for output in 0..bits {
+            let flips = flips[input * bits + output];
+            let expected = expected[output];
+            let rate = flips as f64 / trials as f64;
+            total_flips += flips;
+            total_trials += trials;
+            worst_bias = worst_bias.max((rate - expected).abs());
+
+            let deviation = (trials as f64 * expected * (1.0 - expected)).sqrt();
+            if deviation > 0.0 {
+                worst_z = worst_z.max((flips as f64 - trials as f64 * expected).abs() / deviation);
+                pairs += 1;
+            }
+        }
+    }
+
+    // the chance of the largest deviation among every pair, with the Sidak correction
+    let p_value = match pairs {
+        0 => 1.0,
+        pairs => -(pairs as f64 * (-normal_test(worst_z)).ln_1p()).exp_m1(),
+    };
+    AvalancheTest {
+        mean: total_flips as f64 / total_trials as f64,
+        worst_bias,
+        p_value,
+    }
+}
+
+/// The gap test of outputs in the lower half of the domain, where each gap is the number of outputs
+/// between consecutive outputs in the lower half, against a geometric distribution.
+fn gaps<T>(domain: &Domain<T>, positions: &[u128]) -> ChiSquaredTest
+where
+    T: SequenceInt,
+{
+    let half = domain.len.map_or(1 << 127, |len| len / 2);
+    let p = half as f64 / domain.size();
+
+    let mut lengths = vec::Vec::new();
+    let mut last = None;
+    for (i, &position) in positions.iter().enumerate() {
+        if position < half {
+            if let Some(last) = last {
+                lengths.push(i - last - 1);
+            }
+            last = Some(i);
+        }
+    }
+
+    // the gaps of length [0, classes) each have their own class, and longer gaps share the tail
+    let gaps = lengths.len() as f64;
+    let mut classes = 1;
+    while classes < MAX_GAP_CLASSES && gaps * (1.0 - p).powi(classes as i32 + 1) >= MIN_EXPECTED {
+        classes += 1;
+    }
+
+    let mut counts = vec![0usize; classes + 1];
+    for length in lengths {
+        counts[length.min(classes)] += 1;
+    }
+    let statistic = counts
+        .iter()
+        .enumerate()
+        .map(|(length, &count)| {
+            let probability = match length == classes {
+                true => (1.0 - p).powi(classes as i32),
+                false => p * (1.0 - p).powi(length as i32),
+            };
+            let expected = gaps * probability;
+            (count as f64 - expected).powi(2) / expected
+        })
+        .sum::<f64>();
+    chi_squared_test(statistic, classes)
+}
+
+/// The correlation with the sequences built from the next `seed_base` and the next `seed_offset`.
+fn seed_correlation<T, P>(sequence: &RandomSequence<T, P>, domain: &Domain<T>, positions: &[u128]) -> CorrelationTest
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+    let next = |seed: T| T::from_unsigned(seed.to_unsigned().wrapping_add(&T::Unsigned::one()));
+    let mut base = sequence.config;
+    base.seed_base = next(base.seed_base);
+    let mut offset = sequence.config;
+    offset.seed_offset = next(offset.seed_offset);
+
+    let worst = [base, offset]
+        .into_iter()
+        .map(|config| {
+            let neighbour = config.into_iter_with(sequence.permutation.clone());
+            correlation(
+                positions.iter().map(|&p| domain.fraction(p)),
+                (0..positions.len()).map(|i| domain.fraction(domain.position(neighbour.n_unsigned(index(i))))),
+            )
+        })
+        .fold(
+            CorrelationTest {
+                coefficient: 0.0,
+                p_value: 1.0,
+            },
+            |worst, test| match test.p_value < worst.p_value {
+                true => test,
+                false => worst,
+            },
+        );
+    CorrelationTest {
+        coefficient: worst.coefficient,
+        p_value: 1.0 - (1.0 - worst.p_value).powi(2),
+    }
+}
+
+/// The chi-squared test of a statistic with `degrees_of_freedom`.
+fn chi_squared_test(statistic: f64, degrees_of_freedom: usize) -> ChiSquaredTest {
+    let p_value = gamma_q(degrees_of_freedom as f64 / 2.0, statistic / 2.0);
+    ChiSquaredTest {
+        statistic,
+        degrees_of_freedom,
+        p_value,
+    }
+}
+
+/// The two sided p value of a standard normal `z`, `erfc(|z| / sqrt(2))`.
+fn normal_test(z: f64) -> f64 {
+    gamma_q(0.5, z * z / 2.0)
+}
+
+/// The regularized upper incomplete gamma function `Q(a, x)`, using the series expansion below
+/// `a + 1` and the continued fraction above.
+fn gamma_q(a: f64, x: f64) -> f64 {
+    const ITERATIONS: usize = 1000;
+    const EPSILON: f64 = 1e-15;
+    const TINY: f64 = 1e-300;
+
+    if x <= 0.0 {
+        return 1.0;
+    }
+    let prefix = (a * x.ln() - x - ln_gamma(a)).exp();
+
+    if x < a + 1.0 {
+        let (mut term, mut sum, mut n) = (1.0 / a, 1.0 / a, a);
+        for _ in 0..ITERATIONS {
+            n += 1.0;
+            term *= x / n;
+            sum += term;
+            if term.abs() < sum.abs() * EPSILON {
+                break;
+            }
+        }
+        return (1.0 - sum * prefix).clamp(0.0, 1.0);
+    }
+
+    // the modified Lentz algorithm
+    let mut b = x + 1.0 - a;
+    let mut c = 1.0 / TINY;
+    let mut d = 1.0 / b;
+    let mut fraction = d;
+    for i in 1..ITERATIONS {
+        let an = -(i as f64) * (i as f64 - a);
+        b += 2.0;
+        d = an * d + b;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = b + an / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1.0 / d;
+        let delta = d * c;
+        fraction *= delta;
+        if (delta - 1.0).abs() < EPSILON {
+            break;
+        }
+    }
+    (fraction * prefix).clamp(0.0, 1.0)
+}
+
+/// The natural log of the gamma function for `x > 0`, with the Lanczos approximation.
+fn ln_gamma(x: f64) -> f64 {
+    const G: f64 = 7.0;
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x < 0.5 {
+        // the reflection formula, gamma(x) * gamma(1 - x) = pi / sin(pi * x)
+        return (core::f64::consts::PI / (core::f64::consts::PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+
+    let x = x - 1.0;
+    let mut sum = COEFFICIENTS[0];
+    for (i, coefficient) in COEFFICIENTS.iter().enumerate().skip(1) {
+        sum += coefficient / (x + i as f64);
+    }
+    let t = x + G + 0.5;
+    0.5 * (2.0 * core::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use statrs::distribution::{ChiSquared, ContinuousCDF, Normal};
+
+    use super::*;
+    use crate::builder::RandomSequenceBuilder;
+
+    #[test]
+    fn test_p_values() {
+        for degrees_of_freedom in [1usize, 2, 5, 10, 99, 1023] {
+            let distribution = ChiSquared::new(degrees_of_freedom as f64).unwrap();
+            for statistic in [0.01, 0.5, 1.0, 3.0, 10.0, 50.0, 100.0, 1000.0, 1200.0] {
+                let expected = 1.0 - distribution.cdf(statistic);
+                let p_value = chi_squared_test(statistic, degrees_of_freedom).p_value;
+                assert!(
+                    (p_value - expected).abs() < 1e-9,
+                    "{} {}: {} != {}",
+                    statistic,
+                    degrees_of_freedom,
+                    p_value,
+                    expected
+                );
+            }
+        }
+
+        let normal = Normal::new(0.0, 1.0).unwrap();
+        for z in [0.0, 0.1, 1.0, 1.96, 3.0, -3.0, 6.0] {
+            let expected = 2.0 * (1.0 - normal.cdf(f64::abs(z)));
+            assert!((normal_test(z) - expected).abs() < 1e-9, "{}: {} != {}", z, normal_test(z), expected);
+        }
+    }
+
+    macro_rules! test_analyze {
+        ($name:ident, $type:ident) => {
+            #[test]
+            fn $name() {
+                // the sequences are deterministic, and every test passes for the current constants
+                for (seed_base, seed_offset) in [(0, 0), (1, 7), ($type::MAX, 42)] {
+                    let config = RandomSequenceBuilder::<$type>::new(seed_base, seed_offset);
+                    for sequence in [config.into_iter(), config.with_len(200).into_iter()] {
+                        let report = analyze(&sequence, 4_000);
+                        assert!(report.passed(0.001), "{:?}", report);
+                        assert_eq!(report, analyze(&sequence, 4_000));
+                    }
+                }
+            }
+        };
+    }
+
+    test_analyze!(test_u8_analyze, u8);
+    test_analyze!(test_u16_analyze, u16);
+    test_analyze!(test_u32_analyze, u32);
+    test_analyze!(test_u64_analyze, u64);
+    test_analyze!(test_u128_analyze, u128);
+    test_analyze!(test_usize_analyze, usize);
+    test_analyze!(test_i32_analyze, i32);
+
+    /// A permutation which is only a xor, and so fails every test except uniformity of a full domain.
+    #[derive(Debug, Clone)]
+    struct Xor;
+
+    impl Permutation<u32> for Xor {
+        fn apply(&self, x: u32) -> u32 {
+            x ^ 0x5a5a5
+        }
+    }
+
+    #[test]
+    fn test_analyze_weak_permutation() {
+        let sequence = RandomSequenceBuilder::<u32>::new(0, 0).with_len(1 << 20).into_iter_with(Xor);
+        let report = analyze(&sequence, 4_000);
+        assert!(!report.passed(0.001));
+        assert!(report.uniformity.p_value < 1e-9, "{:?}", report.uniformity);
+        assert!(report.serial_correlation.coefficient > 0.9, "{:?}", report.serial_correlation);
+        assert!(report.avalanche.worst_bias > 0.4, "{:?}", report.avalanche);
+        assert!(report.avalanche.p_value < 1e-9, "{:?}", report.avalanche);
+        assert!(report.seed_correlation.p_value < 1e-9, "{:?}", report.seed_correlation);
+    }
+
+    #[test]
+    fn test_analyze_samples() {
+        let sequence = RandomSequenceBuilder::<u8>::new(3, 5).into_iter();
+        let report = analyze(&sequence, 10_000);
+        assert_eq!(report.samples, 256);
+
+        // every value is sampled, so the buckets are exact
+        assert_eq!(report.uniformity.statistic, 0.0);
+        assert_eq!(report.uniformity.p_value, 1.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "analysis requires at least 32 samples")]
+    fn test_analyze_too_few_samples() {
+        analyze(&RandomSequenceBuilder::<u8>::new(3, 5).with_len(16).into_iter(), 10_000);
+    }
+}
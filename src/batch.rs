@@ -0,0 +1,252 @@
+use core::fmt::{Debug, Formatter};
+
+use num_traits::{NumCast, One, WrappingAdd, Zero};
+
+use crate::builder::SequenceInt;
+use crate::permutation::{Permutation, Qpr};
+use crate::sequence::RandomSequence;
+
+/// The number of outputs computed side by side. Each lane is independent, so the compiler is free to
+/// interleave or vectorise the modular multiplications across lanes.
+const LANES: usize = 8;
+
+impl<T, P> RandomSequence<T, P>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+    /// Fill the slice with the next elements in the sequence, returning the number of elements
+    /// written.
+    ///
+    /// Equivalent to calling [RandomSequence::next] for each element of `out`, but faster. Fewer than
+    /// `out.len()` elements are written if the sequence is exhausted first.
+    #[inline]
+    pub fn fill(&mut self, out: &mut [T]) -> usize {
+        if self.ended {
+            return 0;
+        }
+
+        let count = match self.remaining() {
+            Some(remaining) if remaining <= out.len() => remaining,
+            _ => out.len(),
+        };
+
+        let mut index = self.current_index;
+        let mut chunks = out[..count].chunks_exact_mut(LANES);
+        for out in &mut chunks {
+            let mut lanes = [T::Unsigned::zero(); LANES];
+            for lane in lanes.iter_mut() {
+                *lane = self.actual_index(index);
+                index = index.wrapping_add(&T::Unsigned::one());
+            }
+            self.output_lanes(lanes, out);
+        }
+        for out in chunks.into_remainder() {
+            *out = self.n_unsigned(index);
+            index = index.wrapping_add(&T::Unsigned::one());
+        }
+
+        // the final element leaves the index in place and marks the sequence as ended, as next() does
+        if Some(count) == self.remaining() {
+            self.current_index = self.back_index;
+            self.ended = true;
+        } else {
+            // count is less than remaining, and so must fit within T::Unsigned
+            let count: T::Unsigned = NumCast::from(count).expect("count fits within the sequence");
+            self.current_index = self.current_index + count;
+        }
+        count
+    }
+
+    /// Get the elements at each of the `indices`, writing `out[i] = n(indices[i])`.
+    ///
+    /// Equivalent to calling [RandomSequence::n] for each index, but faster.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `indices` and `out` are different lengths.
+    #[inline]
+    pub fn n_many(&self, indices: &[T], out: &mut [T]) {
+        assert_eq!(indices.len(), out.len(), "indices and out must be the same length");
+
+        let mut indices = indices.chunks_exact(LANES);
+        let mut chunks = out.chunks_exact_mut(LANES);
+        for (indices, out) in (&mut indices).zip(&mut chunks) {
+            let mut lanes = [T::Unsigned::zero(); LANES];
+            for (lane, &index) in lanes.iter_mut().zip(indices) {
+                *lane = self.actual_index(self.unsigned_index(index));
+            }
+            self.output_lanes(lanes, out);
+        }
+        for (&index, out) in indices.remainder().iter().zip(chunks.into_remainder()) {
+            *out = self.n(index);
+        }
+    }
+
+    /// Iterate over the next elements in the sequence in arrays of `N` elements.
+    ///
+    /// If the sequence length is not a multiple of `N`, the final elements which do not fill an array
+    /// are left in the sequence, to be consumed with [RandomSequence::next] or [RandomSequence::fill].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is 0.
+    #[inline]
+    pub fn chunks<const N: usize>(&mut self) -> Chunks<'_, T, N, P> {
+        assert!(N > 0, "chunk size must be non-zero");
+        Chunks { sequence: self }
+    }
+
+    /// Permute each of the absolute indices in `lanes`, writing the outputs to `out`.
+    ///
+    /// The lanes are permuted together with [Permutation::apply_many], so that the permutation can
+    /// compute them side by side.
+    #[inline(always)]
+    fn output_lanes(&self, mut lanes: [T::Unsigned; LANES], out: &mut [T]) {
+        self.permutation.apply_many(&mut lanes);
+
+        // cycle-walk the lanes which fall outside of a bounded sequence
+        if let Some(len) = self.config.len {
+            for lane in lanes.iter_mut() {
+                while *lane >= len {
+                    *lane = self.permute(*lane);
+                }
+            }
+        }
+
+        for (out, &lane) in out.iter_mut().zip(lanes.iter()) {
+            *out = self.output(lane);
+        }
+    }
+}
+
+/// An iterator over the elements of a [RandomSequence] in arrays of `N` elements, created by
+/// [RandomSequence::chunks].
+pub struct Chunks<'a, T, const N: usize, P = Qpr<T>>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+    sequence: &'a mut RandomSequence<T, P>,
+}
+
+impl<T, const N: usize, P> Debug for Chunks<'_, T, N, P>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+    RandomSequence<T, P>: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Chunks").field("sequence", &self.sequence).finish()
+    }
+}
+
+impl<T, const N: usize, P> Iterator for Chunks<'_, T, N, P>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+    type Item = [T; N];
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.sequence.remaining().is_some_and(|remaining| remaining < N) {
+            return None;
+        }
+
+        let mut chunk = [T::zero(); N];
+        self.sequence.fill(&mut chunk);
+        Some(chunk)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.sequence.remaining() {
+            Some(remaining) => (remaining / N, Some(remaining / N)),
+            None => (usize::MAX / N, None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec;
+    use std::vec::Vec;
+
+    use crate::builder::RandomSequenceBuilder;
+
+    macro_rules! test_batch {
+        ($name:ident, $type:ident) => {
+            #[test]
+            fn $name() {
+                for config in [
+                    RandomSequenceBuilder::<$type>::new(0, 0),
+                    RandomSequenceBuilder::<$type>::new(0x12, 0x56).with_len(37),
+                    RandomSequenceBuilder::<$type>::new(0x34, 0x78).with_range(20..120),
+                ] {
+                    let expected: Vec<$type> = config.into_iter().take(100).collect();
+
+                    // fill matches next()
+                    let mut sequence = config.into_iter();
+                    let mut out = vec![0 as $type; 100];
+                    let filled = sequence.fill(&mut out[..13]);
+                    let filled = filled + sequence.fill(&mut out[filled..]);
+                    assert_eq!(&out[..filled], &expected[..]);
+                    assert_eq!(sequence.next(), config.into_iter().nth(filled));
+
+                    // n_many matches n()
+                    let indices: Vec<$type> = (0..100).map(|i| (i * 7 % 100) as $type).collect();
+                    let mut out = vec![0 as $type; 100];
+                    sequence.n_many(&indices, &mut out);
+                    for (&index, &num) in indices.iter().zip(out.iter()) {
+                        assert_eq!(sequence.n(index), num);
+                    }
+
+                    // chunks match next()
+                    let mut sequence = config.into_iter();
+                    let chunks: Vec<[$type; 3]> = sequence.chunks::<3>().take(33).collect();
+                    assert_eq!(chunks.concat(), &expected[..chunks.len() * 3]);
+                }
+            }
+        };
+    }
+
+    test_batch!(test_u8_batch, u8);
+    test_batch!(test_u16_batch, u16);
+    test_batch!(test_u32_batch, u32);
+    test_batch!(test_u64_batch, u64);
+    test_batch!(test_u128_batch, u128);
+    test_batch!(test_usize_batch, usize);
+    test_batch!(test_i8_batch, i8);
+    test_batch!(test_i64_batch, i64);
+
+    #[test]
+    fn test_fill_exhausted() {
+        let config = RandomSequenceBuilder::<u8>::new(0, 0);
+        let expected: Vec<u8> = config.into_iter().collect();
+
+        let mut sequence = config.into_iter();
+        let mut out = [0u8; 300];
+        assert_eq!(sequence.fill(&mut out), 256);
+        assert_eq!(&out[..256], &expected[..]);
+        assert!(sequence.exhausted());
+        assert_eq!(sequence.fill(&mut out), 0);
+        assert_eq!(sequence.next(), None);
+
+        // the final element is left in the sequence as it does not fill a chunk
+        let mut sequence = config.with_len(10).into_iter();
+        let chunks: Vec<[u8; 3]> = sequence.chunks::<3>().collect();
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(sequence.len(), 1);
+        assert_eq!(sequence.fill(&mut out), 1);
+        assert!(sequence.exhausted());
+        assert_eq!(sequence.chunks::<1>().next(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "indices and out must be the same length")]
+    fn test_n_many_length_mismatch() {
+        let sequence = RandomSequenceBuilder::<u32>::new(0, 0).into_iter();
+        sequence.n_many(&[1, 2, 3], &mut [0; 2]);
+    }
+}
@@ -1,5 +1,10 @@
-use num_traits::{AsPrimitive, PrimInt, WrappingAdd, WrappingSub};
+use core::ops::{Bound, RangeBounds};
 
+use num_traits::{Bounded, NumCast, One, PrimInt, ToPrimitive, WrappingAdd, WrappingSub, Zero};
+
+use crate::permutation::{Permutation, Qpr};
+use crate::primes;
+use crate::residue::QuadraticResidue;
 use crate::sequence::RandomSequence;
 
 /// The configuration for [RandomSequence], a random unique sequence generator.
@@ -13,15 +18,18 @@ use crate::sequence::RandomSequence;
 ///
 /// Crate versioning will bump:
 /// - _Minor version_: when the hard coded parameters are updated in favour of better ones. It is
-///    safe to serialize the [RandomSequenceBuilder] between minor versions.
-/// - _Major version_: when the sequence generation logic fundamentally changes the sequence,
-///    meaning it would be potentially unsafe to serialize the [RandomSequenceBuilder] between
-///    major crate version changes.
+///   safe to serialize the [RandomSequenceBuilder] between minor versions.
+/// - _Major version_: when the sequence generation logic fundamentally changes the sequence. The
+///   serialized [RandomSequenceBuilder] and its string form record the
+///   [RandomSequenceBuilder::ALGORITHM_VERSION] that produced them, and are rejected by a version
+///   which can't reproduce the same sequence.
+///
+/// The string form from [Display](core::fmt::Display) and [FromStr](core::str::FromStr) is
+/// `ru<version>:<type>:<params>`, such as `ru1:u8:` followed by the hex parameters.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RandomSequenceBuilder<T>
 where
-    T: QuadraticResidue
+    T: SequenceInt,
 {
     /// The seed for the the start index.
     pub seed_base: T,
@@ -31,35 +39,47 @@ where
 
     /// A value used as an xor during initialisation for `start_index = f(seed_base, init_base)` to
     /// deterministically pseudo-randomise it.
-    pub init_base: T,
+    pub init_base: T::Unsigned,
 
     /// A value used as an xor during initialisation for `offset = f(seed_offset, init_offset)` to
     /// deterministically pseudo-randomise it.
-    pub init_offset: T,
+    pub init_offset: T::Unsigned,
 
     /// Should be the largest prime number that fits in type `T` and satisfied `prime = 3 mod 4`.
-    pub prime: T,
+    pub prime: T::Unsigned,
 
     /// A value that provides some noise from the xor to generate a pseudo-uniform distribution.
-    pub intermediate_xor: T,
+    pub intermediate_xor: T::Unsigned,
+
+    /// The number of values in the sequence, which will be a permutation of `[min, min + len)`.
+    /// `None` covers the entire range of type `T`.
+    pub len: Option<T::Unsigned>,
+
+    /// The smallest value in the sequence, which offsets every output.
+    pub min: T,
 }
 
 impl<T> RandomSequenceBuilder<T>
 where
-    T: QuadraticResidue
+    T: SequenceInt,
 {
     /// Initialise a config from stored settings. Not recommended unless you know what you're doing,
     /// or these values have been taken from an already serialized RandomSequenceBuilder.
     ///
-    /// Prefer [RandomSequenceBuilderInit::new] instead.
+    /// Prefer [RandomSequenceBuilder::new] instead.
+    ///
+    /// # Safety
+    ///
+    /// The `prime` must be a prime number satisfying `prime = 3 mod 4`, otherwise the sequence will
+    /// not be unique.
     #[inline]
     pub unsafe fn from_spec(
         seed_base: T,
         seed_offset: T,
-        init_base: T,
-        init_offset: T,
-        prime: T,
-        intermediate_xor: T,
+        init_base: T::Unsigned,
+        init_offset: T::Unsigned,
+        prime: T::Unsigned,
+        intermediate_xor: T::Unsigned,
     ) -> Self {
         Self {
             seed_base,
@@ -68,171 +88,631 @@ where
             init_offset,
             prime,
             intermediate_xor,
+            len: None,
+            min: T::zero(),
         }
     }
 
-    /// Intermediary function to compute the quadratic prime residue.
-    #[inline(always)]
-    pub(crate) fn permute_qpr(&self, x: T) -> T {
-        // The small set of integers out of range are mapped to themselves.
-        if x >= self.prime {
-            return x;
+    /// Initialise a config from stored settings, the safe equivalent of
+    /// [RandomSequenceBuilder::from_spec] which checks the prime with [primes::is_prime].
+    ///
+    /// Returns `None` if `prime` is not a prime number satisfying `prime = 3 mod 4`.
+    #[inline]
+    pub fn try_from_spec(
+        seed_base: T,
+        seed_offset: T,
+        init_base: T::Unsigned,
+        init_offset: T::Unsigned,
+        prime: T::Unsigned,
+        intermediate_xor: T::Unsigned,
+    ) -> Option<Self> {
+        match Self::is_prime_3_mod_4(prime) {
+            // SAFETY: the prime has been checked
+            true => Some(unsafe {
+                Self::from_spec(seed_base, seed_offset, init_base, init_offset, prime, intermediate_xor)
+            }),
+            false => None,
         }
+    }
+
+    /// Replace the prime of the permutation, which is otherwise the largest `prime = 3 mod 4` that fits
+    /// within the smallest power of two domain covering the sequence.
+    ///
+    /// A smaller prime leaves more of the domain unpermuted by each quadratic residue, and so gives a
+    /// less uniform sequence, although it is still unique. Bounding the sequence with
+    /// [RandomSequenceBuilder::with_len] or [RandomSequenceBuilder::with_range] replaces the prime,
+    /// and so must be done first.
+    ///
+    /// Returns `None` if `prime` is not a prime number satisfying `prime = 3 mod 4`, or is outside of
+    /// the power of two domain.
+    #[inline]
+    pub fn with_prime(mut self, prime: T::Unsigned) -> Option<Self> {
+        if prime > self.mask() || !Self::is_prime_3_mod_4(prime) {
+            return None;
+        }
+        self.prime = prime;
+        Some(self)
+    }
+
+    /// Replace the prime of the permutation with the largest `prime = 3 mod 4` below `modulus`, found
+    /// with [primes::largest_3_mod_4_prime_below]. A modulus beyond the power of two domain is capped
+    /// to the domain.
+    ///
+    /// See [RandomSequenceBuilder::with_prime], which this must also be called after.
+    ///
+    /// Returns `None` if `modulus <= 3`, as there is no smaller prime.
+    #[inline]
+    pub fn with_prime_below(self, modulus: T::Unsigned) -> Option<Self> {
+        let modulus = modulus.to_u128().unwrap();
+        let mask = self.mask().to_u128().unwrap();
+
+        // the mask is itself a candidate when the modulus is beyond the domain, as mask = 3 mod 4
+        let prime = match modulus > mask && primes::is_prime(mask) {
+            true => mask,
+            false => primes::largest_3_mod_4_prime_below(modulus.min(mask))?,
+        };
+        self.with_prime(NumCast::from(prime).unwrap())
+    }
+
+    /// Whether `prime` is a prime number satisfying `prime = 3 mod 4`.
+    #[inline]
+    pub(crate) fn is_prime_3_mod_4(prime: T::Unsigned) -> bool {
+        let prime = prime.to_u128().unwrap();
+        prime % 4 == 3 && primes::is_prime(prime)
+    }
+
+    /// Bound the sequence to a permutation of exactly `len` values, `[0, len)`.
+    ///
+    /// The `prime` is replaced with the largest `prime = 3 mod 4` below the smallest power of two
+    /// that covers `len`, and outputs above `len` are cycle-walked back into range. As the prime is
+    /// always within a factor of two of `len`, `n(index)` remains O(1) on average.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is not positive.
+    #[inline]
+    pub fn with_len(self, len: T) -> Self {
+        assert!(len > T::zero(), "RandomSequenceBuilder len must be positive");
+        self.with_domain(T::zero(), Some(len.to_unsigned()))
+    }
+
+    /// Bound the sequence to a permutation of every value that fits in `bits` bits, `[0, 2^bits)`, such
+    /// as 24-bit colours, or 53-bit integers which are exact as JavaScript numbers.
+    ///
+    /// Equivalent to `with_len(2^bits)`, where `bits` equal to the width of `T` covers the entire
+    /// type. The prime is the largest `prime = 3 mod 4` of the width, and as the domain is exactly
+    /// `2^bits` no outputs are cycle-walked. For signed types, the sequence only includes negative
+    /// values when `bits` covers the entire type.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits` is 0, or larger than the width of `T`.
+    #[inline]
+    pub fn with_bits(self, bits: u32) -> Self {
+        let width = T::Unsigned::zero().count_zeros();
+        assert!(bits > 0 && bits <= width, "RandomSequenceBuilder bits out of range");
+        let len = match bits < width {
+            true => Some(T::Unsigned::one() << bits as usize),
+            false => None,
+        };
+        self.with_domain(T::zero(), len)
+    }
+
+    /// Bound the sequence to a permutation of every value in `range`, such as `-500..500`.
+    ///
+    /// See [RandomSequenceBuilder::with_len] for how the `prime` is chosen. An unbounded range
+    /// covers the entire range of type `T`, starting from `T::MIN`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is empty.
+    #[inline]
+    pub fn with_range(self, range: impl RangeBounds<T>) -> Self {
+        let min = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.checked_add(&T::one()).expect("RandomSequenceBuilder range is empty"),
+            Bound::Unbounded => T::min_value(),
+        };
+        let max = match range.end_bound() {
+            Bound::Included(&end) => end,
+            Bound::Excluded(&end) => end.checked_sub(&T::one()).expect("RandomSequenceBuilder range is empty"),
+            Bound::Unbounded => T::max_value(),
+        };
+        assert!(min <= max, "RandomSequenceBuilder range is empty");
 
-        // (x * x) % prime; but done safely to avoid integer overflow on x * x
-        // let xm = MontgomeryInt::new(x, &self.prime);
-        // let residue = (xm * xm).residue();
+        // len overflows to zero when the range covers the entire type
+        let len = max.to_unsigned().wrapping_sub(&min.to_unsigned()).wrapping_add(&T::Unsigned::one());
+        self.with_domain(min, Some(len).filter(|len| !len.is_zero()))
+    }
+
+    /// Set the domain of the sequence to `[min, min + len)`, updating the prime and masking the
+    /// constants to the power of two domain.
+    #[inline]
+    pub(crate) fn with_domain(mut self, min: T, len: Option<T::Unsigned>) -> Self {
+        let width = match len {
+            Some(len) => Self::width(len),
+            None => T::Unsigned::zero().count_zeros(),
+        };
+        let mask = Self::width_mask(width);
+        self.prime = <T::Unsigned as NumCast>::from(PRIMES_3_MOD_4[width as usize - MIN_WIDTH as usize]).unwrap();
+        self.init_base = self.init_base & mask;
+        self.init_offset = self.init_offset & mask;
+        self.intermediate_xor = self.intermediate_xor & mask;
+        self.len = len;
+        self.min = min;
+        self
+    }
+
+    /// The number of bits required to cover `[0, len)`.
+    #[inline]
+    fn width(len: T::Unsigned) -> u32 {
+        let bits = T::Unsigned::zero().count_zeros();
+        (bits - (len - T::Unsigned::one()).leading_zeros()).max(MIN_WIDTH)
+    }
 
-        let residue = x.residue(self.prime);
+    /// A mask covering the lowest `width` bits.
+    #[inline]
+    fn width_mask(width: u32) -> T::Unsigned {
+        T::Unsigned::max_value() >> (T::Unsigned::zero().count_zeros() - width) as usize
+    }
 
-        // Op: `self.prime / 2` the bit shift is used to get around rust types
-        if x <= self.prime >> 1 {
-            residue
-        } else {
-            self.prime - residue
+    /// The mask of the power of two domain that the permutation is computed over, which covers the
+    /// whole sequence.
+    #[inline]
+    pub(crate) fn mask(&self) -> T::Unsigned {
+        match self.len {
+            None => T::Unsigned::max_value(),
+            Some(len) => Self::width_mask(Self::width(len)),
         }
     }
-}
 
-impl<T> IntoIterator for RandomSequenceBuilder<T>
-where
-    T: QuadraticResidue,
-    RandomSequence<T>: Iterator<Item = T>,
-{
-    type Item = T;
-    type IntoIter = RandomSequence<T>;
+    /// Build a [RandomSequence] iterator from this config, using `permutation` in place of the
+    /// default [Qpr] permutation.
+    ///
+    /// The permutation is initialised with [Permutation::init] for the domain of this sequence. Seeds,
+    /// bounds, iteration, and shards work as they do with the default permutation.
+    #[inline]
+    pub fn into_iter_with<P>(self, mut permutation: P) -> RandomSequence<T, P>
+    where
+        P: Permutation<T>,
+    {
+        let qpr = Qpr::new(&self, self.mask());
+        permutation.init(&self, qpr.mask);
+        self.build(&qpr, permutation)
+    }
 
-    /// Build a [RandomSequence] iterator from this config.
+    /// Build the sequence, with the start index derived from `seed_base` by the default permutation.
     #[inline]
-    fn into_iter(self) -> Self::IntoIter {
-        let start_index = self.permute_qpr(self.permute_qpr(self.seed_base).wrapping_add(&self.init_base));
-        let intermediate_offset = self.permute_qpr(self.permute_qpr(self.seed_offset).wrapping_add(&self.init_offset));
+    fn build<P>(self, qpr: &Qpr<T>, permutation: P) -> RandomSequence<T, P>
+    where
+        P: Permutation<T>,
+    {
+        let seed_base = self.seed_base.to_unsigned() & qpr.mask;
+        let start_index = qpr.qpr(qpr.qpr(seed_base).wrapping_add(&self.init_base) & qpr.mask);
+
+        // the start index must fall within the sequence
+        let start_index = match self.len {
+            Some(len) => start_index % len,
+            None => start_index,
+        };
 
+        let last_index = match self.len {
+            Some(len) => len - T::Unsigned::one(),
+            None => T::Unsigned::max_value(),
+        };
         RandomSequence {
             config: self,
+            permutation,
             start_index,
-            current_index: T::zero(),
-            intermediate_offset,
+            current_index: T::Unsigned::zero(),
+            back_index: last_index,
+            first_index: T::Unsigned::zero(),
+            last_index,
             ended: false,
         }
     }
 }
 
-impl RandomSequenceBuilder<u8> {
-    /// Initialise a [RandomSequenceBuilder] from a specific seed pair.
+impl<T> IntoIterator for RandomSequenceBuilder<T>
+where
+    T: SequenceInt,
+{
+    type Item = T;
+    type IntoIter = RandomSequence<T>;
+
+    /// Build a [RandomSequence] iterator from this config.
     #[inline]
-    pub fn new(seed_base: u8, seed_offset: u8) -> Self {
-        Self {
-            seed_base,
-            seed_offset,
-            init_base: 167,
-            init_offset: 181,
-            prime: 251,
-            intermediate_xor: 137,
-        }
+    fn into_iter(self) -> Self::IntoIter {
+        let qpr = Qpr::new(&self, self.mask());
+        self.build(&qpr, qpr)
     }
 }
 
-impl RandomSequenceBuilder<u16> {
-    /// Initialise a [RandomSequenceBuilder] from a specific seed pair.
-    #[inline]
-    pub fn new(seed_base: u16, seed_offset: u16) -> Self {
-        Self {
-            seed_base,
-            seed_offset,
-            init_base: 0x682f,
-            init_offset: 0x4679,
-            prime: 65519,
-            intermediate_xor: 0x5bf0,
-        }
-    }
+/// The smallest permutation domain supported, `[0, 4)`, as there are no `prime = 3 mod 4` below 2.
+pub(crate) const MIN_WIDTH: u32 = 2;
+
+/// The largest prime satisfying `prime = 3 mod 4` below `2^width`, for each width from
+/// [MIN_WIDTH] to 128.
+pub(crate) const PRIMES_3_MOD_4: [u128; 127] = [
+    3,                                       // 2^2 - 1
+    7,                                       // 2^3 - 1
+    11,                                      // 2^4 - 5
+    31,                                      // 2^5 - 1
+    59,                                      // 2^6 - 5
+    127,                                     // 2^7 - 1
+    251,                                     // 2^8 - 5
+    503,                                     // 2^9 - 9
+    1019,                                    // 2^10 - 5
+    2039,                                    // 2^11 - 9
+    4091,                                    // 2^12 - 5
+    8191,                                    // 2^13 - 1
+    16363,                                   // 2^14 - 21
+    32719,                                   // 2^15 - 49
+    65519,                                   // 2^16 - 17
+    131071,                                  // 2^17 - 1
+    262139,                                  // 2^18 - 5
+    524287,                                  // 2^19 - 1
+    1048571,                                 // 2^20 - 5
+    2097143,                                 // 2^21 - 9
+    4194287,                                 // 2^22 - 17
+    8388587,                                 // 2^23 - 21
+    16777199,                                // 2^24 - 17
+    33554383,                                // 2^25 - 49
+    67108859,                                // 2^26 - 5
+    134217487,                               // 2^27 - 241
+    268435399,                               // 2^28 - 57
+    536870879,                               // 2^29 - 33
+    1073741783,                              // 2^30 - 41
+    2147483647,                              // 2^31 - 1
+    4294967291,                              // 2^32 - 5
+    8589934583,                              // 2^33 - 9
+    17179869143,                             // 2^34 - 41
+    34359738319,                             // 2^35 - 49
+    68719476731,                             // 2^36 - 5
+    137438953447,                            // 2^37 - 25
+    274877906899,                            // 2^38 - 45
+    549755813723,                            // 2^39 - 165
+    1099511627563,                           // 2^40 - 213
+    2199023255531,                           // 2^41 - 21
+    4398046511087,                           // 2^42 - 17
+    8796093022151,                           // 2^43 - 57
+    17592186044399,                          // 2^44 - 17
+    35184372088763,                          // 2^45 - 69
+    70368744177643,                          // 2^46 - 21
+    140737488355031,                         // 2^47 - 297
+    281474976710591,                         // 2^48 - 65
+    562949953421231,                         // 2^49 - 81
+    1125899906842511,                        // 2^50 - 113
+    2251799813685119,                        // 2^51 - 129
+    4503599627370323,                        // 2^52 - 173
+    9007199254740847,                        // 2^53 - 145
+    18014398509481951,                       // 2^54 - 33
+    36028797018963799,                       // 2^55 - 169
+    72057594037927931,                       // 2^56 - 5
+    144115188075855859,                      // 2^57 - 13
+    288230376151711687,                      // 2^58 - 57
+    576460752303423263,                      // 2^59 - 225
+    1152921504606846883,                     // 2^60 - 93
+    2305843009213693951,                     // 2^61 - 1
+    4611686018427387847,                     // 2^62 - 57
+    9223372036854775783,                     // 2^63 - 25
+    18446744073709551427,                    // 2^64 - 189
+    36893488147419103183,                    // 2^65 - 49
+    73786976294838206459,                    // 2^66 - 5
+    147573952589676412879,                   // 2^67 - 49
+    295147905179352825731,                   // 2^68 - 125
+    590295810358705651619,                   // 2^69 - 93
+    1180591620717411303151,                  // 2^70 - 273
+    2361183241434822606523,                  // 2^71 - 325
+    4722366482869645213603,                  // 2^72 - 93
+    9444732965739290427323,                  // 2^73 - 69
+    18889465931478580854739,                 // 2^74 - 45
+    37778931862957161709471,                 // 2^75 - 97
+    75557863725914323419019,                 // 2^76 - 117
+    151115727451828646838239,                // 2^77 - 33
+    302231454903657293676391,                // 2^78 - 153
+    604462909807314587352839,                // 2^79 - 249
+    1208925819614629174706111,               // 2^80 - 65
+    2417851639229258349412147,               // 2^81 - 205
+    4835703278458516698824647,               // 2^82 - 57
+    9671406556917033397649311,               // 2^83 - 97
+    19342813113834066795298747,              // 2^84 - 69
+    38685626227668133590597571,              // 2^85 - 61
+    77371252455336267181195223,              // 2^86 - 41
+    154742504910672534362390399,             // 2^87 - 129
+    309485009821345068724780451,             // 2^88 - 605
+    618970019642690137449562111,             // 2^89 - 1
+    1237940039285380274899124191,            // 2^90 - 33
+    2475880078570760549798248403,            // 2^91 - 45
+    4951760157141521099596496747,            // 2^92 - 149
+    9903520314283042199192993767,            // 2^93 - 25
+    19807040628566084398385987479,           // 2^94 - 105
+    39614081257132168796771975131,           // 2^95 - 37
+    79228162514264337593543950319,           // 2^96 - 17
+    158456325028528675187087900531,          // 2^97 - 141
+    316912650057057350374175801279,          // 2^98 - 65
+    633825300114114700748351602543,          // 2^99 - 145
+    1267650600228229401496703205223,         // 2^100 - 153
+    2535301200456458802993406410683,         // 2^101 - 69
+    5070602400912917605986812821471,         // 2^102 - 33
+    10141204801825835211973625642911,        // 2^103 - 97
+    20282409603651670423947251285999,        // 2^104 - 17
+    40564819207303340847894502572019,        // 2^105 - 13
+    81129638414606681695789005143947,        // 2^106 - 117
+    162259276829213363391578010288127,       // 2^107 - 1
+    324518553658426726783156020576119,       // 2^108 - 137
+    649037107316853453566312041152347,       // 2^109 - 165
+    1298074214633706907132624082305003,      // 2^110 - 21
+    2596148429267413814265248164610011,      // 2^111 - 37
+    5192296858534827628530496329219907,      // 2^112 - 189
+    10384593717069655257060992658440059,     // 2^113 - 133
+    20769187434139310514121985316880331,     // 2^114 - 53
+    41538374868278621028243970633760683,     // 2^115 - 85
+    83076749736557242056487941267521467,     // 2^116 - 69
+    166153499473114484112975882535042699,    // 2^117 - 373
+    332306998946228968225951765070086139,    // 2^118 - 5
+    664613997892457936451903530140172219,    // 2^119 - 69
+    1329227995784915872903807060280343811,   // 2^120 - 765
+    2658455991569831745807614120560689079,   // 2^121 - 73
+    5316911983139663491615228241121378191,   // 2^122 - 113
+    10633823966279326983230456482242756367,  // 2^123 - 241
+    21267647932558653966460912964485513051,  // 2^124 - 165
+    42535295865117307932921825928971026423,  // 2^125 - 9
+    85070591730234615865843651857942052727,  // 2^126 - 137
+    170141183460469231731687303715884105727, // 2^127 - 1
+    340282366920938463463374607431768211283, // 2^128 - 173
+];
+
+/// The precomputed constants for modular arithmetic against the prime of a sequence over `T`.
+pub(crate) type Modulus<T> = <<T as SequenceInt>::Unsigned as QuadraticResidue>::Modulus;
+
+mod sealed {
+    /// Prevents [SequenceInt](super::SequenceInt) from being implemented outside of this crate.
+    pub trait Sealed {}
 }
 
-impl RandomSequenceBuilder<u32> {
-    /// Initialise a [RandomSequenceBuilder] from a specific seed pair.
+/// An integer type that a [RandomSequence] can be generated over, implemented for `u8`, `u16`,
+/// `u32`, `u64`, `u128`, `usize`, and the signed `i8`, `i16`, `i32`, `i64`, `i128`, and `isize`.
+///
+/// The sequence is computed over the unsigned type of the same width, and signed types are mapped
+/// to and from their two's complement representation. The trait carries the default parameters of
+/// each type, so that generic code can build a sequence over any of them:
+///
+/// ```
+/// use rand_unique::{RandomSequence, RandomSequenceBuilder, SequenceInt};
+///
+/// fn first<T: SequenceInt>(seed: T) -> Option<T> {
+///     RandomSequence::<T>::new(seed, seed).next()
+/// }
+///
+/// fn last<T: SequenceInt>(seed: T, len: T) -> T {
+///     T::builder(seed, seed).with_len(len).into_iter().last().unwrap()
+/// }
+///
+/// assert_eq!(first(5u8), RandomSequence::<u8>::new(5, 5).next());
+/// assert_eq!(first(5i64), RandomSequence::<i64>::new(5, 5).next());
+/// assert_eq!(last(5u16, 100), RandomSequenceBuilder::<u16>::new(5, 5).with_len(100).into_iter().last().unwrap());
+/// ```
+///
+/// This trait is sealed, and can't be implemented for other types.
+pub trait SequenceInt
+where
+    Self: PrimInt + Default + sealed::Sealed,
+{
+    /// The unsigned type of the same width.
+    type Unsigned: QuadraticResidue;
+
+    /// The name of the type, which tags the string form of a [RandomSequenceBuilder].
+    const NAME: &'static str;
+
+    /// The width of the type in bits.
+    const BITS: u32;
+
+    /// The default prime of [RandomSequenceBuilder::new], the largest `prime = 3 mod 4` that fits in
+    /// the type.
+    const PRIME: Self::Unsigned;
+
+    /// The default [RandomSequenceBuilder::init_base].
+    const INIT_BASE: Self::Unsigned;
+
+    /// The default [RandomSequenceBuilder::init_offset].
+    const INIT_OFFSET: Self::Unsigned;
+
+    /// The default [RandomSequenceBuilder::intermediate_xor].
+    const INTERMEDIATE_XOR: Self::Unsigned;
+
+    /// Reinterpret the bits of this integer as unsigned.
+    fn to_unsigned(self) -> Self::Unsigned;
+
+    /// Reinterpret the bits of an unsigned integer as this type.
+    fn from_unsigned(value: Self::Unsigned) -> Self;
+
+    /// Convert an index into a position in `[0, len)`, returning `None` if it is out of bounds.
+    ///
+    /// Signed indices count backwards from the end of the sequence when negative.
+    fn checked_index(self, len: Self::Unsigned) -> Option<Self::Unsigned>;
+
+    /// Convert an index into a position in `[0, len)`, wrapping around the length.
+    fn wrapping_index(self, len: Self::Unsigned) -> Self::Unsigned;
+
+    /// Convert a position in `[0, len)` into an index, the inverse of [SequenceInt::checked_index].
+    ///
+    /// Signed positions that don't fit are counted backwards from the end of the sequence.
+    fn from_index(index: Self::Unsigned, len: Option<Self::Unsigned>) -> Self;
+
+    /// Initialise a [RandomSequenceBuilder] from a specific seed pair in generic code, the same as
+    /// [RandomSequenceBuilder::new] which is a `const fn` for each concrete type.
+    ///
+    /// The remaining parameters are the defaults of the type, [SequenceInt::PRIME],
+    /// [SequenceInt::INIT_BASE], [SequenceInt::INIT_OFFSET], and [SequenceInt::INTERMEDIATE_XOR].
     #[inline]
-    pub fn new(seed_base: u32, seed_offset: u32) -> Self {
-        Self {
+    fn builder(seed_base: Self, seed_offset: Self) -> RandomSequenceBuilder<Self> {
+        RandomSequenceBuilder {
             seed_base,
             seed_offset,
-            init_base: 0x682f0161,
-            init_offset: 0x46790905,
-            prime: 4294967291,
-            intermediate_xor: 0x5bf03635,
+            init_base: Self::INIT_BASE,
+            init_offset: Self::INIT_OFFSET,
+            prime: Self::PRIME,
+            intermediate_xor: Self::INTERMEDIATE_XOR,
+            len: None,
+            min: Self::zero(),
         }
     }
 }
 
-impl RandomSequenceBuilder<u64> {
-    /// Initialise a [RandomSequenceBuilder] from a specific seed pair.
-    #[inline]
-    pub fn new(seed_base: u64, seed_offset: u64) -> Self {
-        Self {
-            seed_base,
-            seed_offset,
-            init_base: 0x682f01615bf03635,
-            init_offset: 0x46790905682f0161,
-            prime: 18446744073709551427,
-            intermediate_xor: 0x5bf0363546790905,
+/// Implement the `const fn` [RandomSequenceBuilder::new] for a concrete type, as the generic
+/// [SequenceInt::builder] can't be called in a const context.
+macro_rules! impl_builder_new {
+    ($type:ident) => {
+        impl RandomSequenceBuilder<$type> {
+            /// Initialise a [RandomSequenceBuilder] from a specific seed pair.
+            ///
+            /// The remaining parameters are the defaults of the type, [SequenceInt::PRIME],
+            /// [SequenceInt::INIT_BASE], [SequenceInt::INIT_OFFSET], and
+            /// [SequenceInt::INTERMEDIATE_XOR]. Generic code uses [SequenceInt::builder] instead.
+            #[inline]
+            pub const fn new(seed_base: $type, seed_offset: $type) -> Self {
+                Self {
+                    seed_base,
+                    seed_offset,
+                    init_base: <$type as SequenceInt>::INIT_BASE,
+                    init_offset: <$type as SequenceInt>::INIT_OFFSET,
+                    prime: <$type as SequenceInt>::PRIME,
+                    intermediate_xor: <$type as SequenceInt>::INTERMEDIATE_XOR,
+                    len: None,
+                    min: 0,
+                }
+            }
         }
-    }
+    };
 }
 
-impl RandomSequenceBuilder<usize> {
-    /// Initialise a [RandomSequenceBuilder] from a specific seed pair.
-    #[inline]
-    pub fn new(seed_base: usize, seed_offset: usize) -> Self {
-        Self {
-            seed_base,
-            seed_offset,
-            init_base: 0x682f01615bf03635,
-            init_offset: 0x46790905682f0161,
-            prime: 18446744073709551427,
-            intermediate_xor: 0x5bf0363546790905,
+macro_rules! impl_unsigned_int {
+    ($type:ident, $init_base:literal, $init_offset:literal, $intermediate_xor:literal) => {
+        impl sealed::Sealed for $type {}
+
+        impl_builder_new!($type);
+
+        impl SequenceInt for $type {
+            type Unsigned = $type;
+
+            const NAME: &'static str = stringify!($type);
+            const BITS: u32 = $type::BITS;
+            const PRIME: Self::Unsigned = PRIMES_3_MOD_4[($type::BITS - MIN_WIDTH) as usize] as $type;
+            const INIT_BASE: Self::Unsigned = $init_base;
+            const INIT_OFFSET: Self::Unsigned = $init_offset;
+            const INTERMEDIATE_XOR: Self::Unsigned = $intermediate_xor;
+
+            #[inline(always)]
+            fn to_unsigned(self) -> Self::Unsigned {
+                self
+            }
+
+            #[inline(always)]
+            fn from_unsigned(value: Self::Unsigned) -> Self {
+                value
+            }
+
+            #[inline(always)]
+            fn checked_index(self, len: Self::Unsigned) -> Option<Self::Unsigned> {
+                (self < len).then_some(self)
+            }
+
+            #[inline(always)]
+            fn wrapping_index(self, len: Self::Unsigned) -> Self::Unsigned {
+                self % len
+            }
+
+            #[inline(always)]
+            fn from_index(index: Self::Unsigned, _len: Option<Self::Unsigned>) -> Self {
+                index
+            }
         }
-    }
+    };
 }
 
-pub trait QuadraticResidue
-where
-    Self: PrimInt + AsPrimitive<usize> + WrappingAdd + WrappingSub
-{
-    /// Compute the quadratic residue of this number against a prime.
-    fn residue(self, prime: Self) -> Self;
-}
+macro_rules! impl_signed_int {
+    ($type:ident, $unsigned:ident) => {
+        impl sealed::Sealed for $type {}
+
+        impl_builder_new!($type);
+
+        /// The outputs are the same as the unsigned sequence with the same seeds, cast to this type.
+        impl SequenceInt for $type {
+            type Unsigned = $unsigned;
+
+            const NAME: &'static str = stringify!($type);
+            const BITS: u32 = $type::BITS;
+            const PRIME: Self::Unsigned = <$unsigned as SequenceInt>::PRIME;
+            const INIT_BASE: Self::Unsigned = <$unsigned as SequenceInt>::INIT_BASE;
+            const INIT_OFFSET: Self::Unsigned = <$unsigned as SequenceInt>::INIT_OFFSET;
+            const INTERMEDIATE_XOR: Self::Unsigned = <$unsigned as SequenceInt>::INTERMEDIATE_XOR;
+
+            #[inline(always)]
+            fn to_unsigned(self) -> Self::Unsigned {
+                self as $unsigned
+            }
+
+            #[inline(always)]
+            fn from_unsigned(value: Self::Unsigned) -> Self {
+                value as $type
+            }
+
+            #[inline(always)]
+            fn checked_index(self, len: Self::Unsigned) -> Option<Self::Unsigned> {
+                match self >= 0 {
+                    true => (self.unsigned_abs() < len).then_some(self.unsigned_abs()),
+                    false => len.checked_sub(self.unsigned_abs()),
+                }
+            }
+
+            #[inline(always)]
+            fn wrapping_index(self, len: Self::Unsigned) -> Self::Unsigned {
+                match self.unsigned_abs() % len {
+                    remainder if self >= 0 || remainder == 0 => remainder,
+                    remainder => len - remainder,
+                }
+            }
 
-macro_rules! impl_residue {
-    ($base_type:ident, $larger_type:ident) => {
-        impl QuadraticResidue for $base_type {
             #[inline(always)]
-            fn residue(self, prime: Self) -> Self {
-                ((self as $larger_type * self as $larger_type) % prime as $larger_type) as Self
+            fn from_index(index: Self::Unsigned, len: Option<Self::Unsigned>) -> Self {
+                match (index > $type::MAX as $unsigned, len) {
+                    (true, Some(len)) => index.wrapping_sub(len) as $type,
+                    _ => index as $type,
+                }
             }
         }
     };
 }
 
-impl_residue!(u8, u16);
-impl_residue!(u16, u32);
-impl_residue!(u32, u64);
-impl_residue!(u64, u128);
-#[cfg(target_pointer_width = "64")]
-impl_residue!(usize, u128);
-#[cfg(target_pointer_width = "32")]
-impl_residue!(usize, u64);
-#[cfg(not(any(target_pointer_width = "64", target_pointer_width = "32")))]
-compile_error!("Unsupported pointer width.");
+impl_unsigned_int!(u8, 167, 181, 137);
+impl_unsigned_int!(u16, 0x682f, 0x4679, 0x5bf0);
+impl_unsigned_int!(u32, 0x682f0161, 0x46790905, 0x5bf03635);
+impl_unsigned_int!(u64, 0x682f01615bf03635, 0x46790905682f0161, 0x5bf0363546790905);
+impl_unsigned_int!(
+    u128,
+    0x682f01615bf0363546790905682f0161,
+    0x46790905682f01615bf0363546790905,
+    0x5bf0363546790905682f01615bf03635
+);
+impl_unsigned_int!(usize, 0x682f01615bf03635, 0x46790905682f0161, 0x5bf0363546790905);
+impl_signed_int!(i8, u8);
+impl_signed_int!(i16, u16);
+impl_signed_int!(i32, u32);
+impl_signed_int!(i64, u64);
+impl_signed_int!(i128, u128);
+impl_signed_int!(isize, usize);
 
 #[cfg(test)]
 mod tests {
     use std::collections::hash_map::Entry;
-    use std::collections::HashMap;
+    use std::collections::{HashMap, HashSet};
     use std::string::ToString;
 
     use super::*;
 
     /// Check the prime satisfies `p = 3 mod 4`.
-    fn is_3_mod_4(n: u64) -> bool {
+    fn is_3_mod_4(n: u128) -> bool {
         n % 4 == 3
     }
 
@@ -245,31 +725,14 @@ mod tests {
             fn $name() {
                 let config = RandomSequenceBuilder::<$type>::new(0, 0);
                 let config_orig = config.clone();
+                assert_eq!(config, $type::builder(0, 0));
 
-                // check the configured prime number satisfies the requirements
-                // is_prime crate makes this very quick for u64
-                let is_prime_res = is_prime::is_prime(&config.prime.to_string());
-                let is_3_mod_4_res = is_3_mod_4(config.prime as u64);
-
-                let mut found_number = config.prime;
-                if !is_prime_res || !is_3_mod_4_res {
-                    if found_number % 2 == 0 {
-                        found_number -= 1;
-                    }
-
-                    // suggest a suitable prime number (slow, but only run when there's a bad prime)
-                    let mut found_next_prime = false;
-                    let mut found_next_3_mod_4 = false;
-
-                    while !found_next_prime || !found_next_3_mod_4 {
-                        found_number -= 2;
-                        found_next_prime = is_prime::is_prime(&found_number.to_string());
-                        found_next_3_mod_4 = is_3_mod_4(found_number as u64);
-                    }
-                }
-
-                assert!(is_prime_res, "{} is not prime, suggested prime: {}", config.prime, found_number);
-                assert!(is_3_mod_4_res, "{} = 3 mod 4 doesn't hold, suggested prime: {}", config.prime, found_number);
+                // check the configured prime number satisfies the requirements, otherwise suggest the
+                // largest suitable prime below it
+                let prime = config.prime as u128;
+                let suggested = primes::largest_3_mod_4_prime_below(prime);
+                assert!(primes::is_prime(prime), "{} is not prime, suggested prime: {:?}", prime, suggested);
+                assert!(is_3_mod_4(prime), "{} = 3 mod 4 doesn't hold, suggested prime: {:?}", prime, suggested);
 
                 // check config can be cloned and equality tested
                 let sequence = config.into_iter();
@@ -277,9 +740,10 @@ mod tests {
 
                 // check permute_qpr for uniqueness
                 const CHECK: usize = $check;
+                let qpr = Qpr::new(&config, config.mask());
                 let mut nums = HashMap::<$type, usize>::new();
                 for i in 0..CHECK {
-                    let num = config.permute_qpr(i as $type);
+                    let num = qpr.qpr(i as $type);
                     match nums.entry(num) {
                         Entry::Vacant(v) => {
                             v.insert(i);
@@ -298,8 +762,97 @@ mod tests {
         };
     }
 
+    #[test]
+    fn test_primes_3_mod_4() {
+        for (i, &prime) in PRIMES_3_MOD_4.iter().enumerate() {
+            let width = i as u32 + MIN_WIDTH;
+            assert!(is_prime::is_prime(&prime.to_string()), "{} is not prime", prime);
+            assert!(is_3_mod_4(prime), "{} = 3 mod 4 doesn't hold", prime);
+            assert_eq!(128 - prime.leading_zeros(), width, "{} is not {} bits", prime, width);
+
+            // the largest suitable prime of each width
+            let domain = (u128::MAX >> (128 - width)).saturating_add(1);
+            assert_eq!(primes::largest_3_mod_4_prime_below(domain), Some(prime));
+        }
+    }
+
+    #[test]
+    fn test_with_prime() {
+        let config = RandomSequenceBuilder::<u32>::new(0x12, 0x56).with_len(1000);
+        assert_eq!(config.with_prime(1019), Some(config));
+        assert_eq!(config.with_prime(1013).map(|config| config.prime), None);
+        assert_eq!(config.with_prime(1015), None);
+        assert_eq!(config.with_prime(1031), None);
+        assert_eq!(config.with_prime(991).map(|config| config.prime), Some(991));
+
+        // still a unique permutation with a smaller prime
+        let sequence = config.with_prime(991).unwrap().into_iter();
+        let nums: HashSet<u32> = sequence.clone().collect();
+        assert_eq!(nums, (0..1000).collect());
+        assert_eq!(sequence.index_of(sequence.n(5)), 5);
+
+        assert_eq!(config.with_prime_below(991).map(|config| config.prime), Some(983));
+        assert_eq!(config.with_prime_below(u32::MAX).map(|config| config.prime), Some(1019));
+        assert_eq!(config.with_prime_below(4).map(|config| config.prime), Some(3));
+        assert_eq!(config.with_prime_below(3), None);
+
+        // mersenne primes at the top of the domain
+        let config = RandomSequenceBuilder::<u8>::new(0, 0).with_len(100);
+        assert_eq!(config.with_prime_below(u8::MAX).map(|config| config.prime), Some(127));
+        let config = RandomSequenceBuilder::<u128>::new(0, 0).with_len(u128::MAX >> 1);
+        assert_eq!(config.with_prime_below(u128::MAX).map(|config| config.prime), Some(u128::MAX >> 1));
+    }
+
+    #[test]
+    fn test_try_from_spec() {
+        let config = RandomSequenceBuilder::<u64>::new(0x12, 0x56);
+        let spec = |prime| {
+            RandomSequenceBuilder::<u64>::try_from_spec(
+                config.seed_base,
+                config.seed_offset,
+                config.init_base,
+                config.init_offset,
+                prime,
+                config.intermediate_xor,
+            )
+        };
+        assert_eq!(spec(config.prime), Some(config));
+        assert_eq!(spec(config.prime - 4), None);
+        assert_eq!(spec(18446744073709551557), None);
+        let prime = primes::largest_3_mod_4_prime_below(1 << 40).unwrap() as u64;
+        assert_eq!(spec(prime).map(|config| config.prime), Some(prime));
+    }
+
+    #[test]
+    fn test_with_len() {
+        let config = RandomSequenceBuilder::<u32>::new(0, 0).with_len(1000);
+        assert_eq!(config.len, Some(1000));
+        assert_eq!(config.prime, 1019);
+        assert_eq!(config.mask(), 1023);
+        assert!(config.init_base <= 1023 && config.init_offset <= 1023 && config.intermediate_xor <= 1023);
+
+        let config = RandomSequenceBuilder::<u8>::new(0, 0).with_len(1);
+        assert_eq!(config.prime, 3);
+        assert_eq!(config.mask(), 3);
+
+        let config = RandomSequenceBuilder::<u8>::new(0, 0).with_len(200);
+        assert_eq!(config.prime, 251);
+        assert_eq!(config.mask(), u8::MAX);
+
+        let config = RandomSequenceBuilder::<u64>::new(0, 0);
+        assert_eq!(config.len, None);
+        assert_eq!(config.mask(), u64::MAX);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_with_len_zero() {
+        RandomSequenceBuilder::<u16>::new(0, 0).with_len(0);
+    }
+
     test_config!(test_u8_config, u8, 256);
     test_config!(test_u16_config, u16, 65536);
     test_config!(test_u32_config, u32, 100_000);
     test_config!(test_u64_config, u64, 100_000);
+    test_config!(test_u128_config, u128, 100_000);
 }
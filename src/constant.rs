@@ -0,0 +1,201 @@
+//! Const equivalents of [RandomSequence::new] and [RandomSequence::n], generated for each concrete
+//! type.
+//!
+//! The generic methods are written over [SequenceInt](crate::SequenceInt), and trait methods can't be
+//! called in a const context on the minimum supported rust version. Only construction and indexing
+//! have const equivalents, which write out the integer operations for each type, and their outputs
+//! must match the generic methods exactly. [RandomSequenceBuilder::new] is itself a `const fn`, and
+//! bounded sequences are built at runtime with the generic builder methods.
+
+use crate::builder::{Modulus, RandomSequenceBuilder};
+use crate::permutation::Qpr;
+use crate::sequence::RandomSequence;
+
+macro_rules! const_sequence {
+    ($type:ident) => {
+        impl RandomSequence<$type> {
+            /// Const equivalent of `SequenceInt::wrapping_index`.
+            #[inline(always)]
+            const fn wrapping_index_const(index: $type, len: $type) -> $type {
+                index % len
+            }
+        }
+
+        const_sequence!(@impl $type, $type);
+    };
+    ($type:ident, $unsigned:ident) => {
+        impl RandomSequence<$type> {
+            /// Const equivalent of `SequenceInt::wrapping_index`.
+            #[inline(always)]
+            const fn wrapping_index_const(index: $type, len: $unsigned) -> $unsigned {
+                let remainder = index.unsigned_abs() % len;
+                match index >= 0 || remainder == 0 {
+                    true => remainder,
+                    false => len - remainder,
+                }
+            }
+        }
+
+        const_sequence!(@impl $type, $unsigned);
+    };
+    (@impl $type:ident, $unsigned:ident) => {
+        impl Qpr<$type> {
+            /// Const equivalent of [Qpr::qpr].
+            #[inline(always)]
+            const fn qpr_const(&self, x: $unsigned) -> $unsigned {
+                if x >= self.prime {
+                    return x;
+                }
+
+                let residue = self.modulus.mul_mod(x, x);
+                if x <= self.prime >> 1 {
+                    residue
+                } else {
+                    self.prime - residue
+                }
+            }
+
+            /// Const equivalent of `Permutation::apply`.
+            #[inline(always)]
+            const fn apply_const(&self, x: $unsigned) -> $unsigned {
+                let inner_residue = self.qpr_const(x).wrapping_add(self.intermediate_offset) & self.mask;
+                self.qpr_const(inner_residue ^ self.intermediate_xor)
+            }
+        }
+
+        impl RandomSequence<$type> {
+            /// Const equivalent of [RandomSequence::new], to initialise a random sequence from the
+            /// seeds in a const context.
+            #[inline]
+            pub const fn new_const(seed_base: $type, seed_offset: $type) -> Self {
+                let config = RandomSequenceBuilder::<$type>::new(seed_base, seed_offset);
+                let mut qpr = Qpr::<$type> {
+                    prime: config.prime,
+                    intermediate_xor: config.intermediate_xor,
+                    intermediate_offset: 0,
+                    mask: $unsigned::MAX,
+                    modulus: <Modulus<$type>>::new(config.prime),
+                };
+                let start_index = qpr.qpr_const(qpr.qpr_const(seed_base as $unsigned).wrapping_add(config.init_base));
+                qpr.intermediate_offset =
+                    qpr.qpr_const(qpr.qpr_const(seed_offset as $unsigned).wrapping_add(config.init_offset));
+
+                RandomSequence {
+                    config,
+                    permutation: qpr,
+                    start_index,
+                    current_index: 0,
+                    back_index: $unsigned::MAX,
+                    first_index: 0,
+                    last_index: $unsigned::MAX,
+                    ended: false,
+                }
+            }
+
+            /// Const equivalent of [RandomSequence::n], to get the nth element in the sequence in a
+            /// const context. Bounded sequences built at runtime are also supported.
+            #[inline]
+            pub const fn n_const(&self, index: $type) -> $type {
+                let index = match self.config.len {
+                    None => index as $unsigned,
+                    Some(len) => Self::wrapping_index_const(index, len),
+                };
+
+                // (start_index + index) % len; but done safely to avoid integer overflow
+                let actual_index = match self.config.len {
+                    None => self.start_index.wrapping_add(index),
+                    Some(len) => match index >= len - self.start_index {
+                        true => index - (len - self.start_index),
+                        false => self.start_index + index,
+                    },
+                };
+
+                // cycle-walk the outputs which fall outside of a bounded sequence
+                let mut value = self.permutation.apply_const(actual_index);
+                if let Some(len) = self.config.len {
+                    while value >= len {
+                        value = self.permutation.apply_const(value);
+                    }
+                }
+
+                (self.config.min as $unsigned).wrapping_add(value) as $type
+            }
+        }
+    };
+}
+
+const_sequence!(u8);
+const_sequence!(u16);
+const_sequence!(u32);
+const_sequence!(u64);
+const_sequence!(u128);
+const_sequence!(usize);
+const_sequence!(i8, u8);
+const_sequence!(i16, u16);
+const_sequence!(i32, u32);
+const_sequence!(i64, u64);
+const_sequence!(i128, u128);
+const_sequence!(isize, usize);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Evaluated at compile time, to check the methods are usable in a const context.
+    const CONFIG: RandomSequenceBuilder<i32> = RandomSequenceBuilder::<i32>::new(0x12, 0x56);
+    const SEQUENCE: RandomSequence<u16> = RandomSequence::<u16>::new_const(0x12, 0x56);
+    const FIFTH: u16 = SEQUENCE.n_const(5);
+    static SHARED: RandomSequence<u64> = RandomSequence::<u64>::new_const(0x12, 0x56);
+
+    #[test]
+    fn test_const_eval() {
+        assert_eq!(CONFIG, RandomSequenceBuilder::<i32>::new(0x12, 0x56));
+        assert_eq!(FIFTH, RandomSequence::<u16>::new(0x12, 0x56).n(5));
+        assert_eq!(SHARED.n_const(5), RandomSequence::<u64>::new(0x12, 0x56).n(5));
+    }
+
+    macro_rules! test_const {
+        ($name:ident, $type:ident) => {
+            #[test]
+            fn $name() {
+                for (seed_base, seed_offset) in [(0, 0), (0x12, 0x56), ($type::MAX, $type::MIN)] {
+                    let sequence = RandomSequence::<$type>::new(seed_base, seed_offset);
+                    let sequence_const = RandomSequence::<$type>::new_const(seed_base, seed_offset);
+                    assert_eq!(sequence.config, sequence_const.config);
+                    assert_eq!(sequence.permutation, sequence_const.permutation);
+                    assert_eq!(sequence.start_index, sequence_const.start_index);
+                    assert_eq!(sequence.last_index, sequence_const.last_index);
+
+                    // n_const also indexes the bounded sequences built at runtime
+                    let config = RandomSequenceBuilder::<$type>::new(seed_base, seed_offset);
+                    for sequence in [
+                        sequence,
+                        config.with_len(1).into_iter(),
+                        config.with_len(37).into_iter(),
+                        config.with_len($type::MAX).into_iter(),
+                        config.with_bits(7).into_iter(),
+                    ] {
+                        for index in (0..100).chain($type::MAX - 100..=$type::MAX) {
+                            assert_eq!(sequence.n(index), sequence.n_const(index));
+                            let index = index.wrapping_add($type::MIN);
+                            assert_eq!(sequence.n(index), sequence.n_const(index));
+                        }
+                    }
+                }
+            }
+        };
+    }
+
+    test_const!(test_u8_const, u8);
+    test_const!(test_u16_const, u16);
+    test_const!(test_u32_const, u32);
+    test_const!(test_u64_const, u64);
+    test_const!(test_u128_const, u128);
+    test_const!(test_usize_const, usize);
+    test_const!(test_i8_const, i8);
+    test_const!(test_i16_const, i16);
+    test_const!(test_i32_const, i32);
+    test_const!(test_i64_const, i64);
+    test_const!(test_i128_const, i128);
+    test_const!(test_isize_const, isize);
+}
@@ -0,0 +1,253 @@
+use core::fmt::{Debug, Formatter};
+
+use num_traits::One;
+
+use crate::builder::SequenceInt;
+use crate::permutation::{Permutation, Qpr};
+use crate::sequence::RandomSequence;
+
+/// A single position in a [RandomSequence], which moves forwards and backwards through the sequence,
+/// created by [RandomSequence::into_cursor].
+///
+/// Unlike iterating with [RandomSequence::next] and [RandomSequence::next_back], which take each
+/// element once from either end, [Cursor::prev] steps back over the element just returned by
+/// [Cursor::next], and the wrapping methods cycle around the ends of the sequence, or of its shard.
+///
+/// ```
+/// use rand_unique::RandomSequence;
+///
+/// let sequence = RandomSequence::<u64>::new(1, 2);
+/// let mut cursor = sequence.clone().into_cursor();
+///
+/// assert_eq!(cursor.next(), Some(sequence.n(0)));
+/// assert_eq!(cursor.prev(), Some(sequence.n(0)));
+/// assert_eq!(cursor.prev(), None);
+/// assert_eq!(cursor.wrapping_prev(), sequence.n(u64::MAX));
+/// assert_eq!(cursor.wrapping_next(), sequence.n(u64::MAX));
+/// assert_eq!(cursor.next(), Some(sequence.n(0)));
+/// ```
+#[derive(Clone)]
+pub struct Cursor<T, P = Qpr<T>>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+    /// The sequence, with `current_index` as the cursor and `back_index` left on `last_index`.
+    sequence: RandomSequence<T, P>,
+}
+
+impl<T, P> Cursor<T, P>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+    /// Start a cursor at the front cursor of `sequence`, or at the end of the sequence if it's
+    /// exhausted. The back cursor of the sequence is discarded.
+    #[inline]
+    pub fn new(mut sequence: RandomSequence<T, P>) -> Self {
+        if sequence.ended {
+            sequence.current_index = sequence.last_index;
+        }
+        sequence.back_index = sequence.last_index;
+        Self { sequence }
+    }
+
+    /// Get the element at the cursor and move forwards, or `None` once the end of the sequence has
+    /// been reached.
+    #[allow(clippy::should_implement_trait)]
+    #[inline]
+    pub fn next(&mut self) -> Option<T> {
+        self.sequence.next()
+    }
+
+    /// Move backwards and get the element at the cursor, or `None` at the start of the sequence.
+    ///
+    /// The opposite of [Cursor::next], such that `prev()` returns the element just returned by
+    /// `next()`, except at the end of the sequence where the cursor stays on the final index.
+    #[inline]
+    pub fn prev(&mut self) -> Option<T> {
+        // decrement then compute, opposite to next()
+        let sequence = &mut self.sequence;
+        if sequence.current_index == sequence.first_index || sequence.is_empty_shard() {
+            return None;
+        }
+        sequence.current_index = sequence.current_index - T::Unsigned::one();
+        sequence.ended = false;
+        Some(sequence.n_unsigned(sequence.current_index))
+    }
+
+    /// Get the element at the cursor and move forwards, cycling the sequence once we reach the end.
+    ///
+    /// This will ignore the exhaustion of the sequence, and the cursor is no longer exhausted
+    /// afterwards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cursor is over an empty shard, which has no element to cycle around.
+    #[inline]
+    pub fn wrapping_next(&mut self) -> T {
+        let sequence = &mut self.sequence;
+        assert!(!sequence.is_empty_shard(), "RandomSequence shard is empty");
+        let next = sequence.n_unsigned(sequence.current_index);
+        sequence.current_index = match sequence.current_index == sequence.last_index {
+            true => sequence.first_index,
+            false => sequence.current_index + T::Unsigned::one(),
+        };
+        sequence.ended = false;
+        next
+    }
+
+    /// Move backwards and get the element at the cursor, cycling the sequence once we reach the
+    /// start.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cursor is over an empty shard, which has no element to cycle around.
+    #[inline]
+    pub fn wrapping_prev(&mut self) -> T {
+        // decrement then compute, opposite to next()
+        let sequence = &mut self.sequence;
+        assert!(!sequence.is_empty_shard(), "RandomSequence shard is empty");
+        sequence.current_index = match sequence.current_index == sequence.first_index {
+            true => sequence.last_index,
+            false => sequence.current_index - T::Unsigned::one(),
+        };
+        sequence.ended = false;
+        sequence.n_unsigned(sequence.current_index)
+    }
+
+    /// Get the index of the cursor, or `None` once the end of the sequence has been reached.
+    #[inline]
+    pub fn index(&self) -> Option<T> {
+        self.sequence.index()
+    }
+
+    /// Move the cursor to `index`, as with [RandomSequence::set_index].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is outside of the sequence or its shard.
+    #[inline]
+    pub fn set_index(&mut self, index: T) {
+        self.sequence.set_index(index);
+    }
+
+    /// Check if the cursor has reached the end of the sequence.
+    #[inline]
+    pub fn exhausted(&self) -> bool {
+        self.sequence.ended
+    }
+
+    /// The sequence, with its front cursor at the cursor.
+    #[inline]
+    pub fn get_ref(&self) -> &RandomSequence<T, P> {
+        &self.sequence
+    }
+
+    /// Unwrap the sequence, with its front cursor at the cursor.
+    #[inline]
+    pub fn into_inner(self) -> RandomSequence<T, P> {
+        self.sequence
+    }
+}
+
+impl<T, P> Debug for Cursor<T, P>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+    RandomSequence<T, P>: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Cursor").field("sequence", &self.sequence).finish()
+    }
+}
+
+impl<T, P> RandomSequence<T, P>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+    /// Convert the sequence into a [Cursor] at its front cursor, which moves forwards and backwards
+    /// with [Cursor::next] and [Cursor::prev], and can wrap around the ends of the sequence.
+    #[inline]
+    pub fn into_cursor(self) -> Cursor<T, P> {
+        Cursor::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use crate::RandomSequenceBuilder;
+
+    macro_rules! test_cursor {
+        ($name:ident, $type:ident) => {
+            #[test]
+            fn $name() {
+                for len in [1, 2, 100] {
+                    let sequence = RandomSequenceBuilder::<$type>::new(0x12, 0x56).with_len(len).into_iter();
+                    let nums: Vec<$type> = sequence.clone().collect();
+
+                    // next() and prev() step back and forth over the same element
+                    let mut cursor = sequence.clone().into_cursor();
+                    assert_eq!(cursor.prev(), None);
+                    for &num in &nums[..nums.len() - 1] {
+                        assert_eq!(cursor.next(), Some(num));
+                        assert_eq!(cursor.prev(), Some(num));
+                        assert_eq!(cursor.next(), Some(num));
+                    }
+                    assert_eq!(cursor.next(), nums.last().copied());
+                    assert!(cursor.exhausted());
+                    assert_eq!(cursor.index(), None);
+                    assert_eq!(cursor.next(), None);
+
+                    // prev() walks back to the start, from the final index
+                    let reversed: Vec<$type> = std::iter::from_fn(|| cursor.prev()).collect();
+                    assert!(reversed.iter().eq(nums.iter().rev().skip(1)));
+                    assert_eq!(cursor.index(), (len > 1).then_some(0));
+
+                    // wrapping cycles around both ends
+                    assert_eq!(cursor.wrapping_prev(), nums[nums.len() - 1]);
+                    assert_eq!(cursor.wrapping_next(), nums[nums.len() - 1]);
+                    let cycled: Vec<$type> = (0..2 * nums.len()).map(|_| cursor.wrapping_next()).collect();
+                    assert!(cycled.iter().eq(nums.iter().chain(nums.iter())));
+
+                    // an exhausted cursor wraps to the start
+                    cursor.set_index(len - 1);
+                    assert_eq!(cursor.next(), Some(nums[nums.len() - 1]));
+                    assert_eq!(cursor.wrapping_next(), nums[nums.len() - 1]);
+                    assert_eq!(cursor.next(), Some(nums[0]));
+
+                    // the back cursor of the sequence is discarded
+                    if len > 1 {
+                        let mut taken = sequence.clone();
+                        taken.next_back();
+                        let mut cursor = taken.into_cursor();
+                        assert!(std::iter::from_fn(|| cursor.next()).eq(nums.iter().copied()));
+                        assert_eq!(cursor.get_ref().size_hint().0, 0);
+                    }
+
+                    // a sequence exhausted in the middle starts the cursor at the end
+                    let mut sequence = sequence.clone();
+                    while sequence.next().is_some() && sequence.next_back().is_some() {}
+                    assert!(sequence.exhausted());
+                    let mut cursor = sequence.clone().into_cursor();
+                    assert_eq!(cursor.next(), None);
+                    assert_eq!(cursor.prev(), (len > 1).then(|| nums[nums.len() - 2]));
+                    assert_eq!(cursor.into_inner().size_hint().0, if len > 1 { 2 } else { 0 });
+                    assert_eq!(sequence.next(), None);
+                }
+            }
+        };
+    }
+
+    test_cursor!(test_u8_cursor, u8);
+    test_cursor!(test_u16_cursor, u16);
+    test_cursor!(test_u32_cursor, u32);
+    test_cursor!(test_u64_cursor, u64);
+    test_cursor!(test_u128_cursor, u128);
+    test_cursor!(test_usize_cursor, usize);
+    test_cursor!(test_i8_cursor, i8);
+    test_cursor!(test_i64_cursor, i64);
+}
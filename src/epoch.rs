@@ -0,0 +1,551 @@
+use core::fmt::{Debug, Formatter};
+use core::iter::FusedIterator;
+
+use num_traits::{Bounded, NumCast, One, ToPrimitive, WrappingAdd, WrappingSub, Zero};
+
+use crate::builder::{RandomSequenceBuilder, SequenceInt};
+use crate::seed::derive;
+use crate::sequence::RandomSequence;
+
+/// An endless sequence which cycles through every value of a [RandomSequence] once per epoch, in a
+/// fresh order each epoch, created by [RandomSequenceBuilder::into_epochs].
+///
+/// Unlike [Cursor::wrapping_next](crate::Cursor::wrapping_next), which replays the same order
+/// after every `len` items, the order of each epoch is derived from the seeds of the builder and the
+/// epoch counter. Any element of any epoch is computed in O(1) with [Epochs::n_global].
+///
+/// With [Epochs::with_window], the last `window` items of each epoch never appear in the first
+/// `window` items of the next, such as to avoid repeating a song across the end of a shuffled
+/// playlist.
+///
+/// ```
+/// use rand_unique::RandomSequenceBuilder;
+///
+/// let config = RandomSequenceBuilder::<u16>::new(1, 2).with_len(300);
+/// let mut playlist = config.into_epochs().with_window(20);
+///
+/// let first: Vec<u16> = playlist.by_ref().take(300).collect();
+/// let second: Vec<u16> = playlist.by_ref().take(300).collect();
+/// assert_eq!(playlist.epoch(), 2);
+/// assert_ne!(first, second);
+/// assert!(second[..20].iter().all(|song| !first[280..].contains(song)));
+/// assert_eq!(playlist.n_global(1, 5), second[5]);
+/// ```
+///
+/// # Windows
+///
+/// To guarantee the window in O(1), each epoch divides the values of the sequence into a front
+/// class, a back class, and the rest, where the front and back classes are a quarter of the values.
+/// Epoch `e` starts with `window` values of its front class and ends with `window` values of its
+/// back class, and the order within each class, and of the rest of the epoch, is random. The classes
+/// are derived from the key and the boundaries either side of the epoch, such that the back class of
+/// each epoch is disjoint from the front class of the next, and otherwise independent between
+/// epochs. The window must therefore be at most a quarter of the length of the sequence.
+#[derive(Clone)]
+pub struct Epochs<T>
+where
+    T: SequenceInt,
+{
+    /// The sequence of the builder, which maps the positions of each epoch to outputs.
+    base: RandomSequence<T>,
+
+    /// A digest of the builder's seeds, which the permutations of each epoch are derived from.
+    key: [u8; 16],
+
+    /// The number of items at the end of an epoch that are kept out of the start of the next.
+    window: T::Unsigned,
+
+    /// The length of the front and back classes of each epoch.
+    class_len: T::Unsigned,
+
+    /// The epoch and index within it of the next element.
+    epoch: u64,
+    index: T::Unsigned,
+
+    /// The permutations of the current epoch.
+    current: Epoch<T>,
+}
+
+/// The classes and permutations of a single epoch, derived from the key and the epoch counter.
+#[derive(Clone)]
+struct Epoch<T>
+where
+    T: SequenceInt,
+{
+    /// The start of the front class in the indices of `base`, which the classes are rotated by.
+    rotation: T::Unsigned,
+
+    /// The start of the back class after the rotation, which is at least `class_len`.
+    back_start: T::Unsigned,
+
+    /// Orders the front and back classes, where the first `window` of each start and end the epoch.
+    front: RandomSequence<T>,
+    back: RandomSequence<T>,
+
+    /// Orders the middle of the epoch, between the windows.
+    middle: RandomSequence<T>,
+}
+
+impl<T> Epochs<T>
+where
+    T: SequenceInt,
+{
+    /// Start at the first epoch of the sequence from `config`, with no window.
+    #[inline]
+    pub fn new(config: RandomSequenceBuilder<T>) -> Self {
+        let base = config.into_iter();
+
+        let mut seeds = [0; 80];
+        let params = [
+            config.seed_base.to_unsigned(),
+            config.seed_offset.to_unsigned(),
+            config.init_base,
+            config.init_offset,
+            config.intermediate_xor,
+        ];
+        for (bytes, param) in seeds.chunks_exact_mut(16).zip(params) {
+            bytes.copy_from_slice(&param.to_u128().unwrap().to_le_bytes());
+        }
+        let key = derive(&seeds, 0).to_le_bytes();
+
+        // len / 4, where len = max + 1 may overflow
+        let max = base.max_index();
+        let one = T::Unsigned::one();
+        let four = one + one + one + one;
+        let class_len = max / four
+            + if max % four == four - one {
+                one
+            } else {
+                T::Unsigned::zero()
+            };
+
+        let window = T::Unsigned::zero();
+        let current = Self::derive(&base, &key, class_len, window, 0);
+        Self {
+            base,
+            key,
+            window,
+            class_len,
+            epoch: 0,
+            index: T::Unsigned::zero(),
+            current,
+        }
+    }
+
+    /// Keep the last `window` items of each epoch out of the first `window` items of the next, which
+    /// restarts from the first epoch.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is more than a quarter of the length of the sequence.
+    #[inline]
+    pub fn with_window(mut self, window: usize) -> Self {
+        let window = <T::Unsigned as NumCast>::from(window).filter(|&window| window <= self.class_len);
+        self.window = window.expect("Epochs window out of range");
+        self.epoch = 0;
+        self.index = T::Unsigned::zero();
+        self.current = self.epoch_at(0);
+        self
+    }
+
+    /// The window between epochs.
+    #[inline]
+    pub fn window(&self) -> usize {
+        self.window.to_usize().unwrap()
+    }
+
+    /// The epoch of the next element.
+    #[inline]
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// The index of the next element within its epoch.
+    #[inline]
+    pub fn index(&self) -> T {
+        T::from_index(self.index, self.base.config.len)
+    }
+
+    /// Move to `index` of `epoch`, such that the next element is `n_global(epoch, index)`. Negative
+    /// indices count backwards from the end of the epoch.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is outside of a sequence bounded with
+    /// [RandomSequenceBuilder::with_len] or [RandomSequenceBuilder::with_range].
+    #[inline]
+    pub fn seek(&mut self, epoch: u64, index: T) {
+        self.index = match self.base.config.len {
+            None => index.to_unsigned(),
+            Some(len) => index.checked_index(len).expect("Epochs index out of bounds"),
+        };
+        self.set_epoch(epoch);
+    }
+
+    /// Get the element at `index` of `epoch` in O(1), wrapping the index around the length of the
+    /// sequence as with [RandomSequence::n].
+    #[inline]
+    pub fn n_global(&self, epoch: u64, index: T) -> T {
+        let index = self.base.unsigned_index(index);
+        match epoch == self.epoch {
+            true => self.n_epoch(&self.current, index),
+            false => self.n_epoch(&self.epoch_at(epoch), index),
+        }
+    }
+
+    /// Move backwards and get the element there, or `None` at the start of the first epoch.
+    ///
+    /// The opposite of [Epochs::next], such that `prev()` returns the element just returned by
+    /// `next()`.
+    #[inline]
+    pub fn prev(&mut self) -> Option<T> {
+        match self.index.is_zero() {
+            true => {
+                self.set_epoch(self.epoch.checked_sub(1)?);
+                self.index = self.base.max_index();
+            }
+            false => self.index = self.index - T::Unsigned::one(),
+        }
+        Some(self.n_epoch(&self.current, self.index))
+    }
+
+    /// Move to `epoch`, deriving its permutations if it has changed.
+    #[inline]
+    fn set_epoch(&mut self, epoch: u64) {
+        if epoch != self.epoch {
+            self.epoch = epoch;
+            self.current = self.epoch_at(epoch);
+        }
+    }
+
+    /// Get the element at `index` of an epoch.
+    #[inline]
+    fn n_epoch(&self, epoch: &Epoch<T>, index: T::Unsigned) -> T {
+        let window = self.window;
+        let class_len = self.class_len;
+        let back_window = self.base.max_index() - window;
+
+        // the index of `base` after the rotation, within the classes of each part of the epoch
+        let index = if index < window {
+            Self::unsigned(&epoch.front, index)
+        } else if index > back_window {
+            epoch.back_start + Self::unsigned(&epoch.back, index - back_window - T::Unsigned::one())
+        } else {
+            let middle = Self::unsigned(&epoch.middle, index - window);
+            let class_rest = class_len - window;
+            if middle < class_rest {
+                Self::unsigned(&epoch.front, window + middle)
+            } else if middle - class_rest < class_rest {
+                epoch.back_start + Self::unsigned(&epoch.back, window + middle - class_rest)
+            } else {
+                // the rest of the values are either side of the back class
+                let rest = middle - class_rest - class_rest;
+                match rest < epoch.back_start - class_len {
+                    true => class_len + rest,
+                    false => rest + class_len + class_len,
+                }
+            }
+        };
+        self.base.n_unsigned(self.add(epoch.rotation, index))
+    }
+
+    /// The element at `index` of a sequence over `[0, len)`.
+    #[inline(always)]
+    fn unsigned(sequence: &RandomSequence<T>, index: T::Unsigned) -> T::Unsigned {
+        sequence.n_unsigned(index).to_unsigned()
+    }
+
+    /// Add two indices modulo the length of the sequence.
+    #[inline(always)]
+    fn add(&self, a: T::Unsigned, b: T::Unsigned) -> T::Unsigned {
+        Self::add_mod(self.base.config.len, a, b)
+    }
+
+    fn add_mod(len: Option<T::Unsigned>, a: T::Unsigned, b: T::Unsigned) -> T::Unsigned {
+        match len {
+            None => a.wrapping_add(&b),
+            Some(len) if a >= len - b => a - (len - b),
+            Some(_) => a + b,
+        }
+    }
+
+    fn sub_mod(len: Option<T::Unsigned>, a: T::Unsigned, b: T::Unsigned) -> T::Unsigned {
+        match len {
+            None => a.wrapping_sub(&b),
+            Some(_) if a >= b => a - b,
+            Some(len) => a + (len - b),
+        }
+    }
+
+    /// Derive the permutations of `epoch`.
+    #[inline]
+    fn epoch_at(&self, epoch: u64) -> Epoch<T> {
+        Self::derive(&self.base, &self.key, self.class_len, self.window, epoch)
+    }
+
+    fn derive(
+        base: &RandomSequence<T>,
+        key: &[u8; 16],
+        class_len: T::Unsigned,
+        window: T::Unsigned,
+        epoch: u64,
+    ) -> Epoch<T> {
+        let len = base.config.len;
+        let mask = T::Unsigned::max_value().to_u128().unwrap();
+        let message = |epoch: u64, role: u64| {
+            let mut message = [0; 32];
+            message[..16].copy_from_slice(key);
+            message[16..24].copy_from_slice(&epoch.to_le_bytes());
+            message[24..].copy_from_slice(&role.to_le_bytes());
+            message
+        };
+
+        // the front class of each epoch starts at a random index, so the front class of the next
+        // epoch starts at the boundary after this one
+        let boundary = |epoch: u64| -> T::Unsigned {
+            let value = derive(&message(epoch, 3), 0);
+            let value = match len {
+                None => value & mask,
+                Some(len) => value % len.to_u128().unwrap(),
+            };
+            NumCast::from(value).unwrap()
+        };
+        let rotation = boundary(epoch);
+        let next = boundary(epoch.wrapping_add(1));
+
+        // the back class ends where the next front class starts, unless that overlaps the front
+        // class of this epoch, in which case it ends where this front class starts
+        let distance = Self::sub_mod(len, next, rotation);
+        let back_start = match !distance.is_zero() && distance < class_len + class_len {
+            true => Self::sub_mod(len, T::Unsigned::zero(), class_len),
+            false => Self::sub_mod(len, distance, class_len),
+        };
+
+        // the middle covers the whole type when there's no window
+        let middle_len = match (len, window.is_zero()) {
+            (None, true) => None,
+            _ => Some(base.max_index() - (window + window) + T::Unsigned::one()),
+        };
+
+        let sequence = |role: u64, len: Option<T::Unsigned>| {
+            let message = message(epoch, role);
+            let value = |i: u64| -> T::Unsigned { NumCast::from(derive(&message, i) & mask).unwrap() };
+            let config = RandomSequenceBuilder::<T> {
+                seed_base: T::from_unsigned(value(0)),
+                seed_offset: T::from_unsigned(value(1)),
+                init_base: value(2),
+                init_offset: value(3),
+                prime: base.config.prime,
+                intermediate_xor: value(4),
+                len: None,
+                min: T::zero(),
+            };
+
+            // the empty classes of a sequence shorter than 4 are never indexed
+            let config = config.with_domain(T::zero(), len.map(|len| len.max(T::Unsigned::one())));
+            config.into_iter()
+        };
+
+        Epoch {
+            rotation,
+            back_start,
+            front: sequence(0, Some(class_len)),
+            back: sequence(1, Some(class_len)),
+            middle: sequence(2, middle_len),
+        }
+    }
+}
+
+impl<T> Debug for Epochs<T>
+where
+    T: SequenceInt + Debug,
+    RandomSequence<T>: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Epochs")
+            .field("sequence", &self.base)
+            .field("window", &self.window())
+            .field("epoch", &self.epoch)
+            .field("index", &self.index())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> Iterator for Epochs<T>
+where
+    T: SequenceInt,
+{
+    type Item = T;
+
+    /// Get the next element, moving on to the next epoch at the end of each epoch. Never returns
+    /// `None`.
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.n_epoch(&self.current, self.index);
+        match self.index == self.base.max_index() {
+            true => {
+                self.index = T::Unsigned::zero();
+                self.set_epoch(self.epoch.wrapping_add(1));
+            }
+            false => self.index = self.index + T::Unsigned::one(),
+        }
+        Some(next)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+impl<T> FusedIterator for Epochs<T> where T: SequenceInt {}
+
+impl<T> RandomSequenceBuilder<T>
+where
+    T: SequenceInt,
+{
+    /// Build an endless [Epochs] sequence from this config, which cycles through every value of the
+    /// sequence in a fresh order each epoch.
+    #[inline]
+    pub fn into_epochs(self) -> Epochs<T> {
+        Epochs::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::vec::Vec;
+
+    use super::*;
+
+    macro_rules! test_epochs {
+        ($name:ident, $type:ident) => {
+            #[test]
+            fn $name() {
+                for len in [1, 2, 3, 10, 100] {
+                    let config = RandomSequenceBuilder::<$type>::new(0x12, 0x56).with_len(len);
+                    let values: HashSet<$type> = config.into_iter().collect();
+
+                    for window in [0, 1, len as usize / 4].into_iter().filter(|&window| window <= len as usize / 4) {
+                        let mut epochs = config.into_epochs().with_window(window);
+                        assert_eq!(epochs.window(), window);
+                        let orders: Vec<Vec<$type>> =
+                            (0..6).map(|_| epochs.by_ref().take(len as usize).collect()).collect();
+                        assert_eq!(epochs.epoch(), 6);
+                        assert_eq!(epochs.index(), 0);
+
+                        for (epoch, order) in orders.iter().enumerate() {
+                            // each epoch is a permutation of the sequence
+                            assert_eq!(order.iter().copied().collect::<HashSet<_>>(), values);
+                            for (index, &num) in order.iter().enumerate() {
+                                assert_eq!(epochs.n_global(epoch as u64, index as $type), num);
+                            }
+                        }
+
+                        // the end of each epoch is kept out of the start of the next
+                        for pair in orders.windows(2) {
+                            let end: HashSet<$type> = pair[0][len as usize - window..].iter().copied().collect();
+                            assert!(pair[1][..window].iter().all(|num| !end.contains(num)));
+                        }
+                        if len >= 10 {
+                            assert!(orders.windows(2).all(|pair| pair[0] != pair[1]));
+                        }
+
+                        // prev() walks back through the epochs to the start
+                        let reversed: Vec<$type> = std::iter::from_fn(|| epochs.prev()).collect();
+                        assert!(reversed.iter().eq(orders.iter().flatten().rev()));
+                        assert_eq!((epochs.epoch(), epochs.index()), (0, 0));
+
+                        // seek to any epoch
+                        epochs.seek(4, len - 1);
+                        assert_eq!(epochs.next(), orders[4].last().copied());
+                        assert_eq!(epochs.next(), Some(orders[5][0]));
+                        assert_eq!(epochs.prev(), Some(orders[5][0]));
+                        assert_eq!(epochs.prev(), orders[4].last().copied());
+                    }
+                }
+
+                // the full domain of the type, with a window of a quarter
+                let config = RandomSequenceBuilder::<$type>::new(0x12, 0x56);
+                let window = ($type::MAX.to_unsigned() / 4) as usize;
+                let epochs = config.into_epochs().with_window(window);
+                let len = $type::MAX.to_unsigned().wrapping_add(1);
+                let count = window.min(100) as $type;
+                for epoch in [0, 1, 2, u64::MAX] {
+                    let front: HashSet<$type> =
+                        (0..count).map(|i| epochs.n_global(epoch, $type::from_unsigned(i))).collect();
+                    let back: HashSet<$type> = (1..=count)
+                        .map(|i| epochs.n_global(epoch, $type::from_unsigned(len.wrapping_sub(i))))
+                        .collect();
+                    assert_eq!(front.len(), count as usize);
+                    assert!(front.is_disjoint(&back));
+                    let next: HashSet<$type> =
+                        (0..count).map(|i| epochs.n_global(epoch.wrapping_add(1), $type::from_unsigned(i))).collect();
+                    assert!(next.is_disjoint(&back));
+                }
+            }
+        };
+    }
+
+    test_epochs!(test_u8_epochs, u8);
+    test_epochs!(test_u16_epochs, u16);
+    test_epochs!(test_u32_epochs, u32);
+    test_epochs!(test_u64_epochs, u64);
+    test_epochs!(test_u128_epochs, u128);
+    test_epochs!(test_usize_epochs, usize);
+
+    #[test]
+    fn test_epochs_full() {
+        for window in [0, 1, 64] {
+            let mut epochs = RandomSequenceBuilder::<i8>::new(3, 4).into_epochs().with_window(window);
+            let orders: Vec<Vec<i8>> = (0..4).map(|_| epochs.by_ref().take(256).collect()).collect();
+            for order in &orders {
+                assert_eq!(order.iter().collect::<HashSet<_>>().len(), 256);
+            }
+            for pair in orders.windows(2) {
+                assert_ne!(pair[0], pair[1]);
+                assert!(pair[1][..window].iter().all(|num| !pair[0][256 - window..].contains(num)));
+            }
+            assert_eq!(epochs.n_global(1, -1), orders[1][255]);
+        }
+    }
+
+    #[test]
+    fn test_epochs_range() {
+        let config = RandomSequenceBuilder::<i32>::new(1, 2).with_range(-500..500);
+        let mut epochs = config.into_epochs().with_window(100);
+        for _ in 0..3 {
+            let order: HashSet<i32> = epochs.by_ref().take(1000).collect();
+            assert_eq!(order, (-500..500).collect());
+        }
+    }
+
+    #[test]
+    fn test_epochs_classes() {
+        // the opening values of each epoch aren't drawn from a fixed class every third epoch
+        let config = RandomSequenceBuilder::<u16>::new(1, 2).with_len(1000);
+        let epochs = config.into_epochs().with_window(50);
+        let openings: Vec<HashSet<u16>> =
+            (0..30).map(|epoch| (0..50).map(|i| epochs.n_global(epoch, i)).collect()).collect();
+        for epoch in 0..27 {
+            for offset in 1..=3 {
+                assert_ne!(openings[epoch], openings[epoch + offset]);
+            }
+        }
+        let every_third: HashSet<u16> = openings.iter().step_by(3).flatten().copied().collect();
+        assert!(every_third.len() > 1000 / 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Epochs window out of range")]
+    fn test_epochs_window() {
+        RandomSequenceBuilder::<u32>::new(1, 2).with_len(10).into_epochs().with_window(3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Epochs index out of bounds")]
+    fn test_epochs_seek() {
+        RandomSequenceBuilder::<u32>::new(1, 2).with_len(10).into_epochs().seek(1, 10);
+    }
+}
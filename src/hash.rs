@@ -0,0 +1,97 @@
+//! SipHash-2-4, as a `const fn` that is stable across platforms and crate versions.
+
+/// SipHash-2-4 of `bytes` with the 128-bit key `(k0, k1)`.
+#[inline]
+pub(crate) const fn siphash(k0: u64, k1: u64, bytes: &[u8]) -> u64 {
+    let mut v = init(k0, k1);
+
+    let mut i = 0;
+    while i + 8 <= bytes.len() {
+        let mut block = [0; 8];
+        let mut j = 0;
+        while j < 8 {
+            block[j] = bytes[i + j];
+            j += 1;
+        }
+        v = compress(v, u64::from_le_bytes(block));
+        i += 8;
+    }
+
+    // the final block holds the remaining bytes, and the lowest byte of the length
+    let mut last = (bytes.len() as u64) << 56;
+    let mut j = 0;
+    while i + j < bytes.len() {
+        last |= (bytes[i + j] as u64) << (8 * j);
+        j += 1;
+    }
+    v = compress(v, last);
+
+    finalize(v)
+}
+
+/// The initial state for the key `(k0, k1)`.
+#[inline(always)]
+pub(crate) const fn init(k0: u64, k1: u64) -> [u64; 4] {
+    [k0 ^ 0x736f6d6570736575, k1 ^ 0x646f72616e646f6d, k0 ^ 0x6c7967656e657261, k1 ^ 0x7465646279746573]
+}
+
+/// Absorb a little endian 8 byte block of the message, with 2 rounds.
+#[inline(always)]
+pub(crate) const fn compress(mut v: [u64; 4], block: u64) -> [u64; 4] {
+    v[3] ^= block;
+    v = sip_round(sip_round(v));
+    v[0] ^= block;
+    v
+}
+
+/// Compute the hash from the state after the final block, with 4 rounds.
+#[inline(always)]
+pub(crate) const fn finalize(mut v: [u64; 4]) -> u64 {
+    v[2] ^= 0xff;
+    v = sip_round(sip_round(sip_round(sip_round(v))));
+    v[0] ^ v[1] ^ v[2] ^ v[3]
+}
+
+#[inline(always)]
+const fn sip_round(mut v: [u64; 4]) -> [u64; 4] {
+    v[0] = v[0].wrapping_add(v[1]);
+    v[1] = v[1].rotate_left(13);
+    v[1] ^= v[0];
+    v[0] = v[0].rotate_left(32);
+    v[2] = v[2].wrapping_add(v[3]);
+    v[3] = v[3].rotate_left(16);
+    v[3] ^= v[2];
+    v[0] = v[0].wrapping_add(v[3]);
+    v[3] = v[3].rotate_left(21);
+    v[3] ^= v[0];
+    v[2] = v[2].wrapping_add(v[1]);
+    v[1] = v[1].rotate_left(17);
+    v[1] ^= v[2];
+    v[2] = v[2].rotate_left(32);
+    v
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_siphash() {
+        use core::hash::{Hasher, SipHasher};
+
+        let bytes: Vec<u8> = (0..64).collect();
+        for (k0, k1) in [(0, 0), (0x0706050403020100, 0x0f0e0d0c0b0a0908), (u64::MAX, 1)] {
+            for len in 0..bytes.len() {
+                let mut hasher = SipHasher::new_with_keys(k0, k1);
+                hasher.write(&bytes[..len]);
+                assert_eq!(siphash(k0, k1, &bytes[..len]), hasher.finish());
+            }
+        }
+
+        // the reference test vector for the 15 byte message 00 01 .. 0e, from the SipHash paper
+        assert_eq!(siphash(0x0706050403020100, 0x0f0e0d0c0b0a0908, &bytes[..15]), 0xa129ca6149be45e5);
+    }
+}
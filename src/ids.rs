@@ -0,0 +1,525 @@
+//! Reversible obfuscation of integer ids as short strings, to expose primary keys as opaque public
+//! ids.
+//!
+//! [Ids] encodes an id as the output of a [RandomSequence] at that index, written in a base62,
+//! base32, or Crockford base32 [Alphabet], and decodes it back with [RandomSequence::index_of]. Both
+//! directions are O(1) and don't allocate.
+//!
+//! ```
+//! use rand_unique::RandomSequenceBuilder;
+//! use rand_unique::ids::{Alphabet, Ids};
+//!
+//! let sequence = RandomSequenceBuilder::<u64>::from_str_key("users").into_iter();
+//! let ids = Ids::new(sequence).with_alphabet(Alphabet::Crockford).with_checksum(true);
+//!
+//! let public = ids.encode(42);
+//! assert_eq!(ids.decode(&public), Ok(42));
+//! assert_eq!(ids.decode(&public.to_ascii_lowercase()), Ok(42));
+//! ```
+//!
+//! The default [Qpr] permutation only obfuscates the ids, as its seeds can be recovered from a few
+//! outputs. Use the `keyed` feature's `Feistel` permutation when the ids must not be predictable.
+
+use core::fmt::{Debug, Display, Formatter};
+use core::ops::Deref;
+
+use num_traits::{NumCast, ToPrimitive, WrappingSub};
+
+use crate::builder::SequenceInt;
+use crate::permutation::{Permutation, Qpr};
+use crate::sequence::RandomSequence;
+
+/// The maximum length of an encoded [Id], including the checksum character.
+const CAPACITY: usize = 64;
+
+/// The set of characters an [Id] is written in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Alphabet {
+    /// `0-9`, `A-Z`, and `a-z`, the shortest ids. Case sensitive.
+    #[default]
+    Base62,
+
+    /// The RFC 4648 base32 alphabet, `A-Z` and `2-7`. Case sensitive.
+    Base32,
+
+    /// Crockford's base32 alphabet, `0-9` and `A-Z` without `I`, `L`, `O`, and `U`. Decoding is case
+    /// insensitive and reads `I` and `L` as `1`, and `O` as `0`, for ids that are read aloud or
+    /// typed by hand.
+    Crockford,
+}
+
+impl Alphabet {
+    /// The characters of each digit, in order.
+    #[inline]
+    const fn symbols(self) -> &'static [u8] {
+        match self {
+            Alphabet::Base62 => b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz",
+            Alphabet::Base32 => b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567",
+            Alphabet::Crockford => b"0123456789ABCDEFGHJKMNPQRSTVWXYZ",
+        }
+    }
+
+    /// The number of digits in the alphabet.
+    #[inline]
+    pub const fn base(self) -> u32 {
+        self.symbols().len() as u32
+    }
+
+    /// The value of a character, or `None` if it's not in the alphabet.
+    #[inline]
+    fn digit(self, symbol: u8) -> Option<u8> {
+        let symbol = match self {
+            Alphabet::Crockford => match symbol.to_ascii_uppercase() {
+                b'I' | b'L' => b'1',
+                b'O' => b'0',
+                upper => upper,
+            },
+            _ => symbol,
+        };
+        self.symbols().iter().position(|&s| s == symbol).map(|digit| digit as u8)
+    }
+}
+
+/// The reasons a string can't be decoded by [Ids::decode].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IdError {
+    /// The string is empty, shorter than the minimum length, or longer than any encoded id.
+    Length,
+
+    /// The string contains a character outside of the alphabet.
+    Character,
+
+    /// The checksum character doesn't match the rest of the id.
+    Checksum,
+
+    /// The string is padded with more leading zero digits than the minimum length requires, so that
+    /// every id has exactly one encoding.
+    Padding,
+
+    /// The decoded value is outside of the domain of the sequence.
+    Range,
+}
+
+impl Display for IdError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            IdError::Length => "id has an invalid length",
+            IdError::Character => "id contains a character outside of the alphabet",
+            IdError::Checksum => "id checksum does not match",
+            IdError::Padding => "id has unnecessary leading zero digits",
+            IdError::Range => "id is outside of the sequence",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for IdError {}
+
+/// An encoded id, a short ASCII string which dereferences to a `str`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Id {
+    bytes: [u8; CAPACITY],
+    len: usize,
+}
+
+impl Id {
+    /// The encoded id.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.bytes[..self.len]).expect("Id is always ASCII")
+    }
+}
+
+impl Deref for Id {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for Id {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Display for Id {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Debug for Id {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        Debug::fmt(self.as_str(), f)
+    }
+}
+
+/// Encodes integer ids as short strings and decodes them back, a bijection between the ids
+/// `[0, len)` of a [RandomSequence] and their strings.
+///
+/// Ids are the indices of the sequence, so a sequence bounded with
+/// [RandomSequenceBuilder::with_len](crate::RandomSequenceBuilder::with_len) encodes `[0, len)` in
+/// as few characters as `len` needs. Negative ids of a signed sequence count backwards from the end
+/// of the sequence, as with [RandomSequence::n].
+#[derive(Clone)]
+pub struct Ids<T, P = Qpr<T>>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+    /// The permutation of the ids.
+    sequence: RandomSequence<T, P>,
+
+    /// The characters of the encoded ids.
+    alphabet: Alphabet,
+
+    /// Whether a checksum character is appended to the encoded ids.
+    checksum: bool,
+
+    /// The minimum length of the encoded ids, including the checksum character.
+    min_len: usize,
+}
+
+impl<T, P> Ids<T, P>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+    /// Encode ids with the permutation of `sequence`, in [Alphabet::Base62] without a checksum.
+    #[inline]
+    pub fn new(sequence: RandomSequence<T, P>) -> Self {
+        Self {
+            sequence,
+            alphabet: Alphabet::Base62,
+            checksum: false,
+            min_len: 0,
+        }
+    }
+
+    /// Write the ids with the characters of `alphabet`.
+    #[inline]
+    pub fn with_alphabet(mut self, alphabet: Alphabet) -> Self {
+        self.alphabet = alphabet;
+        self
+    }
+
+    /// Append a Luhn mod N checksum character to the ids, which catches any single mistyped
+    /// character and most swapped adjacent characters when decoding.
+    #[inline]
+    pub fn with_checksum(mut self, checksum: bool) -> Self {
+        self.checksum = checksum;
+        self
+    }
+
+    /// Pad the ids with leading zero digits to at least `min_len` characters, including the checksum
+    /// character.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min_len` is greater than 64.
+    #[inline]
+    pub fn with_min_len(mut self, min_len: usize) -> Self {
+        assert!(min_len <= CAPACITY, "Ids min_len out of range");
+        self.min_len = min_len;
+        self
+    }
+
+    /// The alphabet the ids are written in.
+    #[inline]
+    pub fn alphabet(&self) -> Alphabet {
+        self.alphabet
+    }
+
+    /// The sequence which permutes the ids.
+    #[inline]
+    pub fn get_ref(&self) -> &RandomSequence<T, P> {
+        &self.sequence
+    }
+
+    /// The minimum number of digits before the checksum character.
+    #[inline]
+    fn min_digits(&self) -> usize {
+        self.min_len.saturating_sub(self.checksum as usize)
+    }
+
+    /// Encode an id as a string.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the id is outside of a sequence bounded with
+    /// [RandomSequenceBuilder::with_len](crate::RandomSequenceBuilder::with_len) or
+    /// [RandomSequenceBuilder::with_range](crate::RandomSequenceBuilder::with_range). Negative ids
+    /// don't count backwards from the end of a bounded sequence, as that would give two ids the same
+    /// string, and are only valid for positions beyond the maximum of a signed type, as returned by
+    /// [Ids::decode].
+    #[inline]
+    pub fn encode(&self, id: T) -> Id {
+        let index = match self.sequence.config.len {
+            None => id.to_unsigned(),
+            Some(len) => id
+                .checked_index(len)
+                .filter(|&index| T::from_index(index, Some(len)) == id)
+                .expect("Ids id out of bounds"),
+        };
+        let value = self.sequence.n_unsigned(index).to_unsigned().wrapping_sub(&self.sequence.config.min.to_unsigned());
+        let mut value = value.to_u128().expect("unsigned integers fit in a u128");
+
+        // the digits from least to most significant
+        let base = self.alphabet.base() as u128;
+        let mut digits = [0u8; CAPACITY];
+        let mut count = 0;
+        loop {
+            digits[count] = (value % base) as u8;
+            value /= base;
+            count += 1;
+            if value == 0 {
+                break;
+            }
+        }
+        count = count.max(self.min_digits());
+
+        let symbols = self.alphabet.symbols();
+        let mut id = Id {
+            bytes: [0; CAPACITY],
+            len: count,
+        };
+        for (i, &digit) in digits[..count].iter().rev().enumerate() {
+            id.bytes[i] = symbols[digit as usize];
+        }
+        if self.checksum {
+            id.bytes[count] = symbols[luhn(self.alphabet.base(), &digits[..count]) as usize];
+            id.len += 1;
+        }
+        id
+    }
+
+    /// Decode a string from [Ids::encode] back to its id.
+    #[inline]
+    pub fn decode(&self, id: &str) -> Result<T, IdError> {
+        let bytes = id.as_bytes();
+        if bytes.len() > CAPACITY || bytes.len() < self.min_len.max(1 + self.checksum as usize) {
+            return Err(IdError::Length);
+        }
+
+        // the digits from least to most significant
+        let count = bytes.len() - self.checksum as usize;
+        let mut digits = [0u8; CAPACITY];
+        for (digit, &symbol) in digits[..count].iter_mut().zip(bytes[..count].iter().rev()) {
+            *digit = self.alphabet.digit(symbol).ok_or(IdError::Character)?;
+        }
+
+        if self.checksum {
+            let check = self.alphabet.digit(bytes[count]).ok_or(IdError::Character)?;
+            if check as u32 != luhn(self.alphabet.base(), &digits[..count]) {
+                return Err(IdError::Checksum);
+            }
+        }
+        if count > self.min_digits().max(1) && digits[count - 1] == 0 {
+            return Err(IdError::Padding);
+        }
+
+        let base = self.alphabet.base() as u128;
+        let value = digits[..count]
+            .iter()
+            .rev()
+            .try_fold(0u128, |value, &digit| value.checked_mul(base)?.checked_add(digit as u128))
+            .ok_or(IdError::Range)?;
+        let value: T::Unsigned = NumCast::from(value).ok_or(IdError::Range)?;
+        if value > self.sequence.max_index() {
+            return Err(IdError::Range);
+        }
+
+        Ok(self.sequence.index_of(self.sequence.output(value)))
+    }
+}
+
+impl<T, P> Debug for Ids<T, P>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+    RandomSequence<T, P>: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Ids")
+            .field("sequence", &self.sequence)
+            .field("alphabet", &self.alphabet)
+            .field("checksum", &self.checksum)
+            .field("min_len", &self.min_len)
+            .finish()
+    }
+}
+
+/// The Luhn mod N check digit of `digits`, ordered from least to most significant.
+#[inline]
+fn luhn(base: u32, digits: &[u8]) -> u32 {
+    let mut factor = 2;
+    let mut sum = 0;
+    for &digit in digits {
+        let addend = factor * digit as u32;
+        sum += addend / base + addend % base;
+        factor = 3 - factor;
+    }
+    (base - sum % base) % base
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::string::String;
+
+    use super::*;
+    use crate::RandomSequenceBuilder;
+
+    const ALPHABETS: [Alphabet; 3] = [Alphabet::Base62, Alphabet::Base32, Alphabet::Crockford];
+
+    macro_rules! test_ids {
+        ($name:ident, $type:ident) => {
+            #[test]
+            fn $name() {
+                let full = RandomSequenceBuilder::<$type>::new(3, 7);
+                for config in [full, full.with_len(100)] {
+                    for alphabet in ALPHABETS {
+                        for (checksum, min_len) in [(false, 0), (true, 0), (false, 12), (true, 12)] {
+                            let ids = Ids::new(config.into_iter())
+                                .with_alphabet(alphabet)
+                                .with_checksum(checksum)
+                                .with_min_len(min_len);
+
+                            let mut seen = HashSet::new();
+                            for id in (0..100).map(|id| id as $type) {
+                                let encoded = ids.encode(id);
+                                assert!(encoded.len() >= min_len);
+                                assert!(encoded.bytes().all(|symbol| alphabet.digit(symbol).is_some()));
+                                assert_eq!(ids.decode(&encoded), Ok(id), "{} {:?}", id, encoded);
+                                assert!(seen.insert(encoded));
+                            }
+
+                            if config.len.is_none() {
+                                for id in [$type::MIN, $type::MAX, $type::MAX - 1] {
+                                    assert_eq!(ids.decode(&ids.encode(id)), Ok(id));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        };
+    }
+
+    test_ids!(test_u8_ids, u8);
+    test_ids!(test_u16_ids, u16);
+    test_ids!(test_u32_ids, u32);
+    test_ids!(test_u64_ids, u64);
+    test_ids!(test_u128_ids, u128);
+    test_ids!(test_usize_ids, usize);
+    test_ids!(test_i8_ids, i8);
+    test_ids!(test_i64_ids, i64);
+
+    #[test]
+    fn test_ids_short() {
+        // a bounded sequence needs only as many digits as its length
+        let ids = Ids::new(RandomSequenceBuilder::<u64>::new(1, 2).with_len(62 * 62).into_iter());
+        assert!((0..62 * 62).all(|id| ids.encode(id).len() <= 2));
+
+        let ids = Ids::new(RandomSequenceBuilder::<i32>::new(1, 2).with_range(-500..500).into_iter());
+        assert_eq!(ids.decode(&ids.encode(999)), Ok(999));
+
+        // positions beyond i8::MAX can only be written as negative ids
+        let ids = Ids::new(RandomSequenceBuilder::<i8>::new(1, 2).with_range(-100..100).into_iter());
+        let decoded: HashSet<i8> = (0..=127).chain(-72..0).map(|id| ids.decode(&ids.encode(id)).unwrap()).collect();
+        assert_eq!(decoded.len(), 200);
+    }
+
+    #[test]
+    #[should_panic(expected = "Ids id out of bounds")]
+    fn test_ids_negative_bounded() {
+        let ids = Ids::new(RandomSequenceBuilder::<i32>::new(1, 2).with_range(-500..500).into_iter());
+        ids.encode(-1);
+    }
+
+    #[test]
+    fn test_ids_crockford() {
+        let ids = Ids::new(RandomSequence::<u64>::new(5, 8)).with_alphabet(Alphabet::Crockford);
+        let encoded = ids.encode(12345);
+        let id = ids.decode(&encoded).unwrap();
+        assert_eq!(ids.decode(&encoded.to_ascii_lowercase()), Ok(id));
+
+        let confused: String = encoded
+            .chars()
+            .map(|c| match c {
+                '0' => 'O',
+                '1' => 'l',
+                c => c,
+            })
+            .collect();
+        assert_eq!(ids.decode(&confused), Ok(id));
+        assert_eq!(ids.decode("U"), Err(IdError::Character));
+    }
+
+    #[test]
+    fn test_ids_checksum() {
+        let sequence = RandomSequence::<u64>::new(5, 8);
+        for alphabet in ALPHABETS {
+            let ids = Ids::new(sequence.clone()).with_alphabet(alphabet).with_checksum(true);
+            let base = alphabet.base() as u8;
+            for id in 0..50 {
+                let encoded = ids.encode(id);
+                let bytes = encoded.as_bytes();
+
+                // every single character change is caught
+                for (i, &symbol) in bytes.iter().enumerate() {
+                    let digit = alphabet.digit(symbol).unwrap();
+                    for other in (0..base).filter(|&other| other != digit) {
+                        let mut changed = encoded;
+                        changed.bytes[i] = alphabet.symbols()[other as usize];
+                        assert_ne!(ids.decode(&changed).ok(), Some(id));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_ids_invalid() {
+        let ids = Ids::new(RandomSequenceBuilder::<u16>::new(1, 1).with_len(1000).into_iter()).with_min_len(4);
+        let encoded = ids.encode(7);
+        assert_eq!(encoded.len(), 4);
+        assert_eq!(ids.decode(&encoded), Ok(7));
+
+        assert_eq!(ids.decode(""), Err(IdError::Length));
+        assert_eq!(ids.decode(&encoded[1..]), Err(IdError::Length));
+        assert_eq!(ids.decode(&"0".repeat(65)), Err(IdError::Length));
+        assert_eq!(ids.decode("00-1"), Err(IdError::Character));
+        assert_eq!(ids.decode(&["0", &encoded].concat()), Err(IdError::Padding));
+        assert_eq!(ids.decode("00G8"), Err(IdError::Range));
+        assert_eq!(ids.decode("zzzzzzzzzzzzzzzzzzzzzzzzzzzzzzz"), Err(IdError::Range));
+
+        let ids = ids.with_checksum(true).with_min_len(0);
+        assert_eq!(ids.decode("0"), Err(IdError::Length));
+        assert_eq!(ids.encode(ids.decode("00").unwrap()).as_str(), "00");
+        assert_eq!(ids.decode("01"), Err(IdError::Checksum));
+    }
+
+    #[cfg(feature = "keyed")]
+    #[test]
+    fn test_ids_feistel() {
+        let config = RandomSequenceBuilder::<u64>::new(0, 0);
+        let ids = Ids::new(config.into_iter_with(crate::Feistel::new(*b"0123456789abcdef")));
+        let other = Ids::new(config.into_iter_with(crate::Feistel::new(*b"fedcba9876543210")));
+        for id in [0, 1, 1000, u64::MAX] {
+            assert_eq!(ids.decode(&ids.encode(id)), Ok(id));
+            assert_ne!(ids.encode(id), other.encode(id));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Ids id out of bounds")]
+    fn test_ids_out_of_bounds() {
+        Ids::new(RandomSequenceBuilder::<u32>::new(1, 1).with_len(10).into_iter()).encode(10);
+    }
+}
@@ -0,0 +1,417 @@
+//! A byte stream of sequence outputs, to pipe a [RandomSequence] into external random number test
+//! batteries such as PractRand, dieharder, or TestU01, or to write it to a file.
+//!
+//! ```
+//! use std::io::Read;
+//! use rand_unique::RandomSequence;
+//! use rand_unique::io::SequenceReader;
+//!
+//! let sequence = RandomSequence::<u32>::new(1, 2);
+//! let mut reader = SequenceReader::new(sequence.clone());
+//!
+//! let mut bytes = [0u8; 8];
+//! reader.read_exact(&mut bytes).unwrap();
+//! assert_eq!(bytes[..4], sequence.n(0).to_le_bytes());
+//! assert_eq!(bytes[4..], sequence.n(1).to_le_bytes());
+//! ```
+
+use core::fmt::{Debug, Formatter};
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom};
+
+use num_traits::{NumCast, ToPrimitive};
+
+use crate::builder::SequenceInt;
+use crate::permutation::{Permutation, Qpr};
+use crate::sequence::RandomSequence;
+
+/// The number of outputs computed at once with [RandomSequence::fill].
+const BATCH: usize = 64;
+
+/// Reads the little endian bytes of successive outputs of a [RandomSequence], from its current
+/// index until it's exhausted.
+///
+/// Each output is written as the lowest [SequenceReader::with_width] bytes, the full width of `T` by
+/// default. [Seek] positions are byte offsets from index 0 of the whole sequence, so that output `i`
+/// starts at byte `i * width`. Seeking outside of a shard, or past the outputs already taken from the
+/// back with [RandomSequence::next_back], is an error.
+#[derive(Clone)]
+pub struct SequenceReader<T, P = Qpr<T>>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+    /// The sequence, positioned after the buffered outputs.
+    sequence: RandomSequence<T, P>,
+
+    /// The number of bytes written for each output.
+    width: usize,
+
+    /// The bytes of the buffered outputs, and the range which hasn't been read yet.
+    buffer: [u8; BATCH * 16],
+    start: usize,
+    end: usize,
+}
+
+impl<T, P> SequenceReader<T, P>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+    /// Read the outputs of `sequence`, starting from its current index.
+    #[inline]
+    pub fn new(sequence: RandomSequence<T, P>) -> Self {
+        Self {
+            sequence,
+            width: core::mem::size_of::<T>(),
+            buffer: [0; BATCH * 16],
+            start: 0,
+            end: 0,
+        }
+    }
+
+    /// Write only the lowest `bytes` bytes of each output, such as the low 32 bits of a `u64`.
+    ///
+    /// A partially read output is restarted from its first byte.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes` is zero or larger than the width of `T`.
+    #[inline]
+    pub fn with_width(mut self, bytes: usize) -> Self {
+        assert!(bytes > 0 && bytes <= core::mem::size_of::<T>(), "SequenceReader width out of range");
+        if let Some((index, _)) = self.cursor() {
+            self.sequence.current_index = NumCast::from(index).unwrap();
+            self.sequence.ended = false;
+        }
+        self.width = bytes;
+        self.start = 0;
+        self.end = 0;
+        self
+    }
+
+    /// The number of bytes written for each output.
+    #[inline]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Move to the start of the output at `index`, as with [RandomSequence::set_index].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is outside of the sequence or its shard.
+    #[inline]
+    pub fn seek_index(&mut self, index: T) {
+        self.sequence.set_index(index);
+        self.start = 0;
+        self.end = 0;
+    }
+
+    /// The sequence, positioned after any outputs which have been buffered but not yet read.
+    #[inline]
+    pub fn get_ref(&self) -> &RandomSequence<T, P> {
+        &self.sequence
+    }
+
+    /// Unwrap the sequence, positioned after any outputs which have been buffered but not yet read.
+    #[inline]
+    pub fn into_inner(self) -> RandomSequence<T, P> {
+        self.sequence
+    }
+
+    /// Compute the next batch of outputs into the buffer, leaving it empty if the sequence is
+    /// exhausted.
+    #[inline]
+    fn refill(&mut self) {
+        let mut values = [T::zero(); BATCH];
+        let count = self.sequence.fill(&mut values);
+        for (value, out) in values[..count].iter().zip(self.buffer.chunks_exact_mut(self.width)) {
+            out.copy_from_slice(&value.to_unsigned().to_u128().unwrap().to_le_bytes()[..self.width]);
+        }
+        self.start = 0;
+        self.end = count * self.width;
+    }
+
+    /// The index of the output holding the next byte to be read, and the offset of the byte within
+    /// it, or `None` if every output has been read.
+    #[inline]
+    fn cursor(&self) -> Option<(u128, usize)> {
+        // the buffered outputs are ahead of the sequence, which is on its final index once exhausted
+        let unread = self.end - self.start;
+        let ahead = (unread + self.width - 1) / self.width;
+        let offset = (self.width - unread % self.width) % self.width;
+        let current = self.sequence.current_index.to_u128().unwrap();
+        match (ahead, self.sequence.ended) {
+            (0, true) => None,
+            (ahead, ended) => Some((current - (ahead - ended as usize) as u128, offset)),
+        }
+    }
+
+    /// The byte offset of the next byte to be read, `None` if it overflows.
+    #[inline]
+    fn position(&self) -> Option<u128> {
+        match self.cursor() {
+            Some((index, offset)) => index.checked_mul(self.width as u128)?.checked_add(offset as u128),
+            None => self.sequence.back_index.to_u128().unwrap().checked_add(1)?.checked_mul(self.width as u128),
+        }
+    }
+
+    /// Move to a byte offset, returning `None` if it's outside of the sequence.
+    #[inline]
+    fn seek_position(&mut self, position: u128) -> Option<()> {
+        let (index, offset) = (position / self.width as u128, (position % self.width as u128) as usize);
+        let first = self.sequence.first_index.to_u128().unwrap();
+        let last = self.sequence.back_index.to_u128().unwrap();
+        let end = Some(index) == last.checked_add(1);
+        if index < first || (index > last && !end) || (end && offset > 0) {
+            return None;
+        }
+
+        // the end of the sequence is its final index, marked as exhausted, as next() leaves it
+        let (index, ended) = match end {
+            true => (last, true),
+            false => (index, false),
+        };
+        self.sequence.current_index = NumCast::from(index)?;
+        self.sequence.ended = ended;
+        self.start = 0;
+        self.end = 0;
+        if offset > 0 {
+            self.refill();
+            self.start = offset;
+        }
+        Some(())
+    }
+}
+
+impl<T, P> Debug for SequenceReader<T, P>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+    RandomSequence<T, P>: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SequenceReader")
+            .field("sequence", &self.sequence)
+            .field("width", &self.width)
+            .field("buffered", &(self.end - self.start))
+            .finish()
+    }
+}
+
+impl<T, P> Read for SequenceReader<T, P>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let mut written = 0;
+        while written < buf.len() {
+            if self.start == self.end {
+                self.refill();
+                if self.end == 0 {
+                    break;
+                }
+            }
+            let count = (self.end - self.start).min(buf.len() - written);
+            buf[written..written + count].copy_from_slice(&self.buffer[self.start..self.start + count]);
+            self.start += count;
+            written += count;
+        }
+        Ok(written)
+    }
+}
+
+impl<T, P> Seek for SequenceReader<T, P>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+    /// Seek to a byte offset from index 0 of the whole sequence, where [SeekFrom::End] is the end of
+    /// the whole sequence.
+    ///
+    /// Returns an [ErrorKind::InvalidInput] error if the offset is outside of the sequence or its
+    /// shard, or doesn't fit in a `u64`.
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let len = self.sequence.max_index().to_u128().unwrap().checked_add(1);
+        let total = len.and_then(|len| len.checked_mul(self.width as u128));
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset as u128),
+            SeekFrom::Current(offset) => self.position().and_then(|position| offset_by(position, offset)),
+            SeekFrom::End(offset) => total.and_then(|total| offset_by(total, offset)),
+        };
+
+        let invalid = || Error::new(ErrorKind::InvalidInput, "SequenceReader seek outside of the sequence");
+        let position = position.and_then(|position| u64::try_from(position).ok()).ok_or_else(invalid)?;
+        self.seek_position(position as u128).ok_or_else(invalid)?;
+        Ok(position)
+    }
+
+    fn stream_position(&mut self) -> Result<u64> {
+        self.position()
+            .and_then(|position| u64::try_from(position).ok())
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "SequenceReader position overflows u64"))
+    }
+}
+
+/// Offset a position, returning `None` if it's negative.
+#[inline]
+fn offset_by(position: u128, offset: i64) -> Option<u128> {
+    match offset >= 0 {
+        true => position.checked_add(offset as u128),
+        false => position.checked_sub(offset.unsigned_abs() as u128),
+    }
+}
+
+impl<T, P> RandomSequence<T, P>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+    /// Read the little endian bytes of the outputs from the current index, with a [SequenceReader].
+    #[inline]
+    pub fn into_reader(self) -> SequenceReader<T, P> {
+        SequenceReader::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::builder::RandomSequenceBuilder;
+
+    /// The bytes of every remaining output, truncated to `width`.
+    fn expected<T, P>(sequence: &RandomSequence<T, P>, width: usize) -> Vec<u8>
+    where
+        T: SequenceInt,
+        P: Permutation<T>,
+    {
+        sequence
+            .clone()
+            .flat_map(|value| value.to_unsigned().to_u128().unwrap().to_le_bytes()[..width].to_vec())
+            .collect()
+    }
+
+    /// Read every byte, `chunk` bytes at a time.
+    fn read_all(reader: &mut impl Read, chunk: usize) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut buf = std::vec![0; chunk];
+        loop {
+            match reader.read(&mut buf).unwrap() {
+                0 => return bytes,
+                count => bytes.extend_from_slice(&buf[..count]),
+            }
+        }
+    }
+
+    macro_rules! test_reader {
+        ($name:ident, $type:ident) => {
+            #[test]
+            fn $name() {
+                let size = core::mem::size_of::<$type>();
+                let sequence = RandomSequenceBuilder::<$type>::new(3, 9).with_len(200).into_iter();
+
+                for width in 1..=size {
+                    let expected = expected(&sequence, width);
+                    assert_eq!(expected.len(), 200 * width);
+                    for chunk in [1, 7, 64, 10_000] {
+                        let mut reader = SequenceReader::new(sequence.clone()).with_width(width);
+                        assert_eq!(read_all(&mut reader, chunk), expected);
+                        assert_eq!(reader.stream_position().unwrap(), expected.len() as u64);
+                        assert!(reader.get_ref().exhausted());
+                    }
+
+                    // seek to every position, including part way through an output
+                    let mut reader = sequence.clone().into_reader().with_width(width);
+                    for position in [0, 1, width, 3 * width + 1, 199 * width, 200 * width - 1, 200 * width] {
+                        assert_eq!(reader.seek(SeekFrom::Start(position as u64)).unwrap(), position as u64);
+                        assert_eq!(reader.stream_position().unwrap(), position as u64);
+                        let mut buf = [0; 5];
+                        let count = reader.read(&mut buf).unwrap();
+                        assert_eq!(&buf[..count], &expected[position..(position + 5).min(expected.len())]);
+                        assert_eq!(reader.stream_position().unwrap(), (position + count) as u64);
+                    }
+
+                    reader.seek(SeekFrom::End(-(width as i64) - 1)).unwrap();
+                    assert_eq!(read_all(&mut reader, 3), expected[expected.len() - width - 1..]);
+                    reader.seek(SeekFrom::Current(-2)).unwrap();
+                    assert_eq!(read_all(&mut reader, 3), expected[expected.len() - 2..]);
+                    assert!(reader.seek(SeekFrom::End(1)).is_err());
+                    assert!(reader.seek(SeekFrom::Current(-(expected.len() as i64) - 1)).is_err());
+                }
+            }
+        };
+    }
+
+    test_reader!(test_u8_reader, u8);
+    test_reader!(test_u16_reader, u16);
+    test_reader!(test_u32_reader, u32);
+    test_reader!(test_u64_reader, u64);
+    test_reader!(test_u128_reader, u128);
+    test_reader!(test_usize_reader, usize);
+    test_reader!(test_i32_reader, i32);
+
+    #[test]
+    fn test_reader_index() {
+        let sequence = RandomSequence::<u64>::new(5, 6);
+        let mut reader = SequenceReader::new(sequence.clone());
+        let mut buf = [0; 12];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(reader.stream_position().unwrap(), 12);
+
+        // the index is the start of an output, and the buffered outputs are discarded
+        reader.seek_index(1000);
+        reader.read_exact(&mut buf[..8]).unwrap();
+        assert_eq!(buf[..8], sequence.n(1000).to_le_bytes());
+        assert_eq!(reader.stream_position().unwrap(), 1001 * 8);
+
+        // narrowing the width restarts the partially read output
+        reader.read_exact(&mut buf[..3]).unwrap();
+        let mut reader = reader.with_width(4);
+        assert_eq!(reader.stream_position().unwrap(), 1001 * 4);
+        reader.read_exact(&mut buf[..4]).unwrap();
+        assert_eq!(buf[..4], sequence.n(1001).to_le_bytes()[..4]);
+
+        // the end of a sequence over every u64 is past u64::MAX bytes
+        assert!(reader.seek(SeekFrom::End(0)).is_err());
+        assert_eq!(reader.stream_position().unwrap(), 1002 * 4);
+    }
+
+    #[test]
+    fn test_reader_shard() {
+        let sequence = RandomSequenceBuilder::<u32>::new(1, 2).with_len(100).into_iter();
+        let shard = sequence.shard(1, 4);
+        let mut reader = shard.clone().into_reader();
+        assert_eq!(reader.stream_position().unwrap(), 25 * 4);
+        assert_eq!(read_all(&mut reader, 10), expected(&shard, 4));
+        assert_eq!(reader.stream_position().unwrap(), 50 * 4);
+
+        // positions are within the whole sequence, and seeking outside of the shard is an error
+        assert!(reader.seek(SeekFrom::Start(25 * 4 - 1)).is_err());
+        assert!(reader.seek(SeekFrom::Start(50 * 4 + 1)).is_err());
+        assert!(reader.seek(SeekFrom::End(0)).is_err());
+        assert_eq!(reader.seek(SeekFrom::Start(49 * 4)).unwrap(), 49 * 4);
+        assert_eq!(read_all(&mut reader, 10), sequence.n(49).to_le_bytes());
+    }
+
+    #[test]
+    fn test_reader_back() {
+        // outputs taken from the back aren't read, and can't be seeked to
+        let mut sequence = RandomSequenceBuilder::<u32>::new(1, 2).with_len(100).into_iter();
+        sequence.nth_back(9);
+        let mut reader = sequence.clone().into_reader();
+        assert_eq!(read_all(&mut reader, 10), expected(&sequence, 4));
+        assert_eq!(reader.stream_position().unwrap(), 90 * 4);
+        assert!(reader.seek(SeekFrom::Start(90 * 4 + 1)).is_err());
+        assert_eq!(reader.seek(SeekFrom::Start(89 * 4)).unwrap(), 89 * 4);
+        assert_eq!(read_all(&mut reader, 10), sequence.n(89).to_le_bytes());
+    }
+
+    #[test]
+    #[should_panic(expected = "SequenceReader width out of range")]
+    fn test_reader_width_out_of_range() {
+        SequenceReader::new(RandomSequence::<u32>::new(1, 2)).with_width(5);
+    }
+}
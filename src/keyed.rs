@@ -0,0 +1,261 @@
+//! A keyed pseudo-random permutation, for sequences that must not be predictable from their outputs.
+//!
+//! The permutation is a balanced Feistel network over the power of two domain, with SipHash-2-4 as
+//! the round function keyed by a 128-bit secret key. Unlike the quadratic prime residue, observing
+//! outputs of the sequence does not reveal the key.
+
+use core::fmt::{Debug, Formatter};
+
+use num_traits::{NumCast, PrimInt, ToPrimitive};
+
+use crate::builder::{RandomSequenceBuilder, SequenceInt};
+use crate::hash;
+use crate::permutation::Permutation;
+
+/// The number of Feistel rounds, which must be even to recombine the halves without a final swap.
+const ROUNDS: u64 = 8;
+
+/// A keyed pseudo-random [Permutation] with a 128-bit secret key, such that the sequence can't be
+/// reconstructed from its outputs without the key. Must have the `keyed` feature enabled.
+///
+/// The permutation is a Feistel network with SipHash-2-4 rounds, which is slower than the default
+/// quadratic prime residue permutation. Use it with [RandomSequenceBuilder::into_iter_with], and all
+/// other methods and bounds work as before. The key should be generated from a cryptographically
+/// secure source and kept secret, as with the serialized permutation which includes the key.
+///
+/// Only the key determines the permutation. The `seed_base` and `init_base` of the builder still
+/// choose the index the sequence starts from, but `seed_offset`, `init_offset`, and
+/// `intermediate_xor` are unused, such that builders which only differ in those give the same
+/// sequence for the same key.
+///
+/// ```
+/// use rand_unique::{Feistel, RandomSequenceBuilder};
+///
+/// let key = *b"0123456789abcdef";
+/// let sequence = RandomSequenceBuilder::<u32>::new(0, 0).with_len(1000).into_iter_with(Feistel::new(key));
+/// assert_eq!(sequence.index_of(sequence.n(5)), 5);
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Feistel {
+    key: [u8; 16],
+    half_width: u32,
+}
+
+impl Feistel {
+    /// Initialise the permutation from a 128-bit secret key.
+    #[inline]
+    pub const fn new(key: [u8; 16]) -> Self {
+        Self { key, half_width: 64 }
+    }
+}
+
+impl Debug for Feistel {
+    /// Omits the secret key.
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Feistel").field("half_width", &self.half_width).finish_non_exhaustive()
+    }
+}
+
+impl<T> Permutation<T> for Feistel
+where
+    T: SequenceInt,
+{
+    #[inline]
+    fn init(&mut self, _config: &RandomSequenceBuilder<T>, mask: T::Unsigned) {
+        self.half_width = half_width(mask.count_ones());
+    }
+
+    #[inline]
+    fn apply(&self, x: T::Unsigned) -> T::Unsigned {
+        let y = permute(&self.key, self.half_width, x.to_u128().unwrap());
+        <T::Unsigned as NumCast>::from(y).unwrap()
+    }
+
+    #[inline]
+    fn invert(&self, y: T::Unsigned) -> Option<T::Unsigned> {
+        let x = invert(&self.key, self.half_width, y.to_u128().unwrap());
+        <T::Unsigned as NumCast>::from(x)
+    }
+}
+
+/// Permute `x` within the domain `[0, 2^(2 * half_width))`.
+#[inline]
+pub(crate) const fn permute(key: &[u8; 16], half_width: u32, x: u128) -> u128 {
+    let half_mask = half_mask(half_width);
+    let (k0, k1) = split_key(key);
+    let mut left = x >> half_width;
+    let mut right = x & half_mask;
+
+    let mut round = 0;
+    while round < ROUNDS {
+        let next = left ^ (siphash(k0, k1, round, right as u64) as u128 & half_mask);
+        left = right;
+        right = next;
+        round += 1;
+    }
+
+    (left << half_width) | right
+}
+
+/// The inverse of [permute], running the rounds in reverse.
+#[inline]
+pub(crate) const fn invert(key: &[u8; 16], half_width: u32, y: u128) -> u128 {
+    let half_mask = half_mask(half_width);
+    let (k0, k1) = split_key(key);
+    let mut left = y >> half_width;
+    let mut right = y & half_mask;
+
+    let mut round = ROUNDS;
+    while round > 0 {
+        round -= 1;
+        let previous = right ^ (siphash(k0, k1, round, left as u64) as u128 & half_mask);
+        right = left;
+        left = previous;
+    }
+
+    (left << half_width) | right
+}
+
+/// The width of each half of the Feistel network to cover a domain of `width` bits. Odd widths are
+/// rounded up, and the extra outputs are cycle-walked by bounded sequences.
+#[inline(always)]
+pub(crate) const fn half_width(width: u32) -> u32 {
+    (width + 1) / 2
+}
+
+#[inline(always)]
+const fn half_mask(half_width: u32) -> u128 {
+    u128::MAX >> (128 - half_width)
+}
+
+#[inline(always)]
+const fn split_key(key: &[u8; 16]) -> (u64, u64) {
+    let mut k0 = [0; 8];
+    let mut k1 = [0; 8];
+    let mut i = 0;
+    while i < 8 {
+        k0[i] = key[i];
+        k1[i] = key[i + 8];
+        i += 1;
+    }
+    (u64::from_le_bytes(k0), u64::from_le_bytes(k1))
+}
+
+/// SipHash-2-4 of the 16 byte message `value || round`, both little endian.
+#[inline]
+const fn siphash(k0: u64, k1: u64, round: u64, value: u64) -> u64 {
+    // the final block holds the message length of 16 bytes, with no remaining message bytes
+    let v = hash::compress(hash::compress(hash::init(k0, k1), value), round);
+    hash::finalize(hash::compress(v, 16 << 56))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::format;
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::RandomSequence;
+
+    const KEY: [u8; 16] = *b"0123456789abcdef";
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_siphash() {
+        use core::hash::{Hasher, SipHasher};
+
+        let (k0, k1) = split_key(&KEY);
+        for (round, value) in [(0u64, 0u64), (1, 2), (7, u64::MAX), (3, 0x0123456789abcdef)] {
+            let mut hasher = SipHasher::new_with_keys(k0, k1);
+            hasher.write(&value.to_le_bytes());
+            hasher.write(&round.to_le_bytes());
+            assert_eq!(siphash(k0, k1, round, value), hasher.finish());
+        }
+    }
+
+    #[test]
+    fn test_feistel() {
+        for width in [2, 3, 8, 17, 64, 128] {
+            let half_width = half_width(width);
+            let domain = u128::MAX >> (128 - 2 * half_width);
+            for x in (0..=domain.min(1000)).chain(domain.saturating_sub(1000)..=domain) {
+                let y = permute(&KEY, half_width, x);
+                assert!(y <= domain);
+                assert_eq!(invert(&KEY, half_width, y), x);
+            }
+        }
+
+        // every input has a unique output
+        let outputs: HashSet<u128> = (0..1 << 16).map(|x| permute(&KEY, 8, x)).collect();
+        assert_eq!(outputs.len(), 1 << 16);
+    }
+
+    #[test]
+    fn test_debug_omits_key() {
+        let sequence = RandomSequenceBuilder::<u32>::new(0x12, 0x56).into_iter_with(Feistel::new(KEY));
+        for debug in [format!("{:?}", Feistel::new(KEY)), format!("{:?}", sequence), format!("{:#?}", sequence)] {
+            assert!(!debug.contains("key"), "{}", debug);
+            assert!(!debug.contains(&format!("{:?}", KEY)), "{}", debug);
+        }
+    }
+
+    macro_rules! test_keyed {
+        ($name:ident, $type:ident) => {
+            #[test]
+            fn $name() {
+                let config = RandomSequenceBuilder::<$type>::new(0x12, 0x56);
+                let sequence = config.into_iter_with(Feistel::new(KEY));
+                assert_ne!(
+                    sequence.clone().take(10).collect::<Vec<_>>(),
+                    RandomSequence::<$type>::new(0x12, 0x56).take(10).collect::<Vec<_>>(),
+                );
+
+                // a different key gives a different sequence
+                let mut other_key = KEY;
+                other_key[15] ^= 1;
+                assert_ne!(
+                    sequence.clone().take(10).collect::<Vec<_>>(),
+                    config.into_iter_with(Feistel::new(other_key)).take(10).collect::<Vec<_>>(),
+                );
+
+                // only the key determines the permutation, the seeds only choose the start index
+                let offset = RandomSequenceBuilder::<$type>::new(0x12, 0x78).into_iter_with(Feistel::new(KEY));
+                assert!(offset.clone().take(10).eq(sequence.clone().take(10)));
+                let base = RandomSequenceBuilder::<$type>::new(0x34, 0x56).into_iter_with(Feistel::new(KEY));
+                let start = sequence.index_of(base.n(0));
+                assert!((0..10).all(|i| base.n(i) == sequence.n(start.wrapping_add(i))));
+
+                // unique and invertible
+                let nums: HashSet<$type> = sequence.clone().take(100).collect();
+                assert_eq!(nums.len(), 100);
+                for i in 0..100 {
+                    assert_eq!(sequence.index_of(sequence.n(i)), i);
+                }
+
+                // bounded, with odd and even widths
+                for len in [1, 2, 3, 5, 100] {
+                    let mut sequence = config.with_len(len).into_iter_with(Feistel::new(KEY));
+                    let nums: HashSet<$type> = sequence.clone().collect();
+                    assert_eq!(nums, (0..len).collect());
+                    assert_eq!(sequence.next(), Some(sequence.n(0)));
+                    assert_eq!(sequence.next_back(), (len > 1).then(|| sequence.n(len - 1)));
+
+                    let mut out = [0; 100];
+                    sequence.n_many(&[0; 100], &mut out);
+                    assert!(out.iter().all(|&num| num == sequence.n(0)));
+                }
+            }
+        };
+    }
+
+    test_keyed!(test_u8_keyed, u8);
+    test_keyed!(test_u16_keyed, u16);
+    test_keyed!(test_u32_keyed, u32);
+    test_keyed!(test_u64_keyed, u64);
+    test_keyed!(test_u128_keyed, u128);
+    test_keyed!(test_usize_keyed, usize);
+    test_keyed!(test_i8_keyed, i8);
+    test_keyed!(test_i64_keyed, i64);
+}
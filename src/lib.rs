@@ -1,16 +1,60 @@
 #![doc = include_str!("../README.md")]
 #![warn(missing_docs)]
 #![no_std]
-#[cfg(test)]
+#[cfg(any(test, feature = "std"))]
 extern crate std;
 
 #[doc(inline)]
-pub use crate::builder::RandomSequenceBuilder;
+pub use crate::batch::Chunks;
+#[doc(inline)]
+pub use crate::builder::{RandomSequenceBuilder, SequenceInt};
+#[doc(inline)]
+pub use crate::cursor::Cursor;
+#[doc(inline)]
+pub use crate::epoch::Epochs;
+#[cfg(feature = "keyed")]
+#[doc(inline)]
+pub use crate::keyed::Feistel;
+#[doc(inline)]
+pub use crate::permutation::{Permutation, Qpr};
+#[cfg(feature = "rayon")]
+#[doc(inline)]
+pub use crate::rayon::ParIter;
 #[doc(inline)]
 pub use crate::sequence::RandomSequence;
+#[doc(inline)]
+pub use crate::slice::RandomSequenceSlice;
+#[doc(inline)]
+pub use crate::state::StateError;
 
+#[cfg(feature = "analysis")]
+pub mod analysis;
+mod batch;
 mod builder;
+mod constant;
+mod cursor;
+mod epoch;
+mod hash;
+pub mod ids;
+#[cfg(feature = "std")]
+pub mod io;
+#[cfg(feature = "keyed")]
+mod keyed;
+mod permutation;
+pub mod primes;
 #[cfg(feature = "rand")]
 mod rand;
+#[cfg(feature = "rayon")]
+mod rayon;
+// public only for the benches to compare the modular reductions with the bench feature, not part of
+// the api
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub mod residue;
+#[cfg(not(feature = "bench"))]
+mod residue;
+pub mod sample;
 mod seed;
 mod sequence;
+mod slice;
+mod state;
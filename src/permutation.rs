@@ -0,0 +1,375 @@
+//! The bijections that a [RandomSequence] is built from.
+//!
+//! A sequence maps each index to a value with a [Permutation] over the smallest power of two domain
+//! that covers it, and cycle-walks any values which fall outside of a bounded sequence. Everything
+//! else, iteration, exhaustion, bounds, shards, and seeding, is shared between permutations.
+
+use num_traits::{One, WrappingAdd, WrappingSub, Zero};
+
+use crate::builder::{Modulus, RandomSequenceBuilder, SequenceInt};
+use crate::residue::QuadraticResidue;
+#[cfg(doc)]
+use crate::sequence::RandomSequence;
+
+/// A bijection over the power of two domain `[0, mask]`, which a [RandomSequence] uses to map each
+/// index to a unique value.
+///
+/// The default is [Qpr], the quadratic prime residue permutation. Other permutations are used by
+/// building the sequence with [RandomSequenceBuilder::into_iter_with].
+pub trait Permutation<T>: Clone
+where
+    T: SequenceInt,
+{
+    /// Prepare the permutation for the sequence defined by `config`, over the domain `[0, mask]`,
+    /// where `mask` is one less than the smallest power of two that covers the sequence.
+    ///
+    /// Called once when the sequence is built. Does nothing by default.
+    #[inline]
+    fn init(&mut self, _config: &RandomSequenceBuilder<T>, _mask: T::Unsigned) {}
+
+    /// Permute `x`, which must map every value in `[0, mask]` to a unique value in `[0, mask]`.
+    fn apply(&self, x: T::Unsigned) -> T::Unsigned;
+
+    /// Permute each of the `values` in place, equivalent to calling [Permutation::apply] for each.
+    ///
+    /// Permutations can override this to compute several values side by side.
+    #[inline]
+    fn apply_many(&self, values: &mut [T::Unsigned]) {
+        for value in values.iter_mut() {
+            *value = self.apply(*value);
+        }
+    }
+
+    /// The inverse of [Permutation::apply], such that `invert(apply(x)) == Some(x)`, or `None` if
+    /// the permutation can't be inverted. Returns `None` by default.
+    #[inline]
+    fn invert(&self, _y: T::Unsigned) -> Option<T::Unsigned> {
+        None
+    }
+}
+
+/// The quadratic prime residue permutation, the default [Permutation] for a [RandomSequence].
+///
+/// `qpr(qpr(x) + intermediate_offset & mask ^ intermediate_xor)`, where `qpr(x)` is the quadratic
+/// residue of `x` modulo the prime, or its complement for the upper half of the prime. Not
+/// cryptographically secure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Qpr<T>
+where
+    T: SequenceInt,
+{
+    pub(crate) prime: T::Unsigned,
+    pub(crate) intermediate_xor: T::Unsigned,
+    pub(crate) intermediate_offset: T::Unsigned,
+    pub(crate) mask: T::Unsigned,
+    pub(crate) modulus: Modulus<T>,
+}
+
+impl<T> Qpr<T>
+where
+    T: SequenceInt,
+{
+    /// The default permutation of the sequence defined by `config`, over the smallest power of two
+    /// domain that covers it.
+    ///
+    /// Used to compose [Qpr] into another [Permutation], whose [Permutation::init] receives the same
+    /// config.
+    ///
+    /// ```
+    /// use rand_unique::{Permutation, Qpr, RandomSequenceBuilder};
+    ///
+    /// let config = RandomSequenceBuilder::<u16>::new(1, 2).with_len(1000);
+    /// let qpr = Qpr::from_config(&config);
+    /// assert_eq!(config.into_iter_with(qpr).n(5), config.into_iter().n(5));
+    /// assert!(qpr.apply(5) < 1024);
+    /// ```
+    #[inline]
+    pub fn from_config(config: &RandomSequenceBuilder<T>) -> Self {
+        Self::new(config, config.mask())
+    }
+
+    /// The permutation for the sequence defined by `config`, over the domain `[0, mask]`.
+    #[inline]
+    pub(crate) fn new(config: &RandomSequenceBuilder<T>, mask: T::Unsigned) -> Self {
+        let mut qpr = Self {
+            prime: config.prime,
+            intermediate_xor: config.intermediate_xor,
+            intermediate_offset: T::Unsigned::zero(),
+            mask,
+            modulus: T::Unsigned::modulus(config.prime),
+        };
+        let seed_offset = config.seed_offset.to_unsigned() & mask;
+        qpr.intermediate_offset = qpr.qpr(qpr.qpr(seed_offset).wrapping_add(&config.init_offset) & mask);
+        qpr
+    }
+
+    /// Intermediary function to compute the quadratic prime residue.
+    #[inline(always)]
+    pub(crate) fn qpr(&self, x: T::Unsigned) -> T::Unsigned {
+        // The small set of integers out of range are mapped to themselves.
+        if x >= self.prime {
+            return x;
+        }
+
+        // (x * x) % prime; but done safely to avoid integer overflow on x * x, and without a
+        // division for the larger types by using barrett or montgomery reduction
+        let residue = x.residue(&self.modulus);
+
+        // Op: `self.prime / 2` the bit shift is used to get around rust types
+        if x <= self.prime >> 1 {
+            residue
+        } else {
+            self.prime - residue
+        }
+    }
+
+    /// A branchless [Qpr::qpr], so that several values can be computed side by side.
+    ///
+    /// The residue is computed for every `x`, and discarded when `x` is outside of the prime. Such `x`
+    /// are clamped below the prime first, as the residue is only defined for values below it.
+    #[inline(always)]
+    fn qpr_branchless(&self, x: T::Unsigned) -> T::Unsigned {
+        let residue = x.min(self.prime - T::Unsigned::one()).residue(&self.modulus);
+        let upper = self.prime.wrapping_sub(&residue);
+        let permuted = if x <= self.prime >> 1 { residue } else { upper };
+        if x >= self.prime {
+            x
+        } else {
+            permuted
+        }
+    }
+
+    /// Invert [Qpr::qpr], such that `invert_qpr(qpr(x)) = x`.
+    ///
+    /// As `prime = 3 mod 4`, exactly one of `y` or `prime - y` is a quadratic residue, and its square
+    /// root is `r = y^((prime + 1) / 4) % prime`. The lower root `x <= prime / 2` maps to `y` directly,
+    /// and the upper root `x > prime / 2` maps to `prime - residue`.
+    #[inline]
+    pub(crate) fn invert_qpr(&self, y: T::Unsigned) -> T::Unsigned {
+        // The small set of integers out of range are mapped to themselves.
+        if y >= self.prime {
+            return y;
+        }
+
+        // Op: `(self.prime + 1) / 4` without overflowing, as prime = 3 mod 4
+        let exp = (self.prime >> 2) + T::Unsigned::one();
+        let half = self.prime >> 1;
+
+        let root = y.pow_mod(exp, &self.modulus);
+        if root.residue(&self.modulus) == y {
+            // y is a quadratic residue, and came from the lower half
+            root.min(self.prime - root)
+        } else {
+            // prime - y is a quadratic residue, and y came from the upper half
+            let root = (self.prime - y).pow_mod(exp, &self.modulus);
+            match root > half {
+                true => root,
+                false => self.prime - root,
+            }
+        }
+    }
+}
+
+impl<T> Permutation<T> for Qpr<T>
+where
+    T: SequenceInt,
+{
+    #[inline]
+    fn init(&mut self, config: &RandomSequenceBuilder<T>, mask: T::Unsigned) {
+        *self = Self::new(config, mask);
+    }
+
+    #[inline(always)]
+    fn apply(&self, x: T::Unsigned) -> T::Unsigned {
+        let inner_residue = self.qpr(x).wrapping_add(&self.intermediate_offset) & self.mask;
+        self.qpr(inner_residue ^ self.intermediate_xor)
+    }
+
+    /// Each step of the permutation is applied to every value before moving on to the next step, so
+    /// that the modular reduction is computed for all values together.
+    #[inline(always)]
+    fn apply_many(&self, values: &mut [T::Unsigned]) {
+        for value in values.iter_mut() {
+            *value = self.qpr_branchless(*value);
+        }
+        for value in values.iter_mut() {
+            *value = (value.wrapping_add(&self.intermediate_offset) & self.mask) ^ self.intermediate_xor;
+        }
+        for value in values.iter_mut() {
+            *value = self.qpr_branchless(*value);
+        }
+    }
+
+    #[inline]
+    fn invert(&self, y: T::Unsigned) -> Option<T::Unsigned> {
+        let inner_residue = self.invert_qpr(y) ^ self.intermediate_xor;
+        Some(self.invert_qpr(inner_residue.wrapping_sub(&self.intermediate_offset) & self.mask))
+    }
+}
+
+/// The serialized form of [Qpr], without the modulus which is recomputed from the prime.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "Qpr")]
+struct QprState<U> {
+    prime: U,
+    intermediate_xor: U,
+    intermediate_offset: U,
+    mask: U,
+}
+
+#[cfg(feature = "serde")]
+impl<T> serde::Serialize for Qpr<T>
+where
+    T: SequenceInt,
+    T::Unsigned: serde::Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        QprState {
+            prime: self.prime,
+            intermediate_xor: self.intermediate_xor,
+            intermediate_offset: self.intermediate_offset,
+            mask: self.mask,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T> serde::Deserialize<'de> for Qpr<T>
+where
+    T: SequenceInt,
+    T::Unsigned: serde::Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let state = QprState::<T::Unsigned>::deserialize(deserializer)?;
+
+        // the modulus is only defined for odd primes
+        let three = T::Unsigned::one() + T::Unsigned::one() + T::Unsigned::one();
+        if state.prime < three || state.prime & T::Unsigned::one() != T::Unsigned::one() {
+            return Err(serde::de::Error::custom("Qpr prime must be an odd prime"));
+        }
+
+        Ok(Self {
+            prime: state.prime,
+            intermediate_xor: state.intermediate_xor,
+            intermediate_offset: state.intermediate_offset,
+            mask: state.mask,
+            modulus: T::Unsigned::modulus(state.prime),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::vec::Vec;
+
+    use super::*;
+
+    macro_rules! test_invert_qpr {
+        ($name:ident, $type:ident, $check:literal) => {
+            #[test]
+            fn $name() {
+                let config = RandomSequenceBuilder::<$type>::new(0, 0);
+                let qpr = Qpr::new(&config, config.mask());
+                for i in 0..$check {
+                    let x = (i as $type).wrapping_mul(0x9e3779b97f4a7c15u64 as $type);
+                    assert_eq!(qpr.invert_qpr(qpr.qpr(x)), x);
+                    assert_eq!(qpr.invert(qpr.apply(x)), Some(x));
+                    let x = $type::MAX - i as $type;
+                    assert_eq!(qpr.invert_qpr(qpr.qpr(x)), x);
+                    assert_eq!(qpr.invert(qpr.apply(x)), Some(x));
+                }
+
+                // check the bounded primes
+                for len in [1, 2, 3, 5, 100, 127] {
+                    let config = RandomSequenceBuilder::<$type>::new(0, 0).with_len(len);
+                    let qpr = Qpr::new(&config, config.mask());
+                    for x in 0..=config.mask() {
+                        assert_eq!(qpr.invert_qpr(qpr.qpr(x)), x);
+                        assert_eq!(qpr.invert(qpr.apply(x)), Some(x));
+                    }
+                }
+            }
+        };
+    }
+
+    test_invert_qpr!(test_u8_invert_qpr, u8, 128);
+    test_invert_qpr!(test_u16_invert_qpr, u16, 32768);
+    test_invert_qpr!(test_u32_invert_qpr, u32, 10_000);
+    test_invert_qpr!(test_u64_invert_qpr, u64, 10_000);
+    test_invert_qpr!(test_u128_invert_qpr, u128, 1_000);
+
+    #[test]
+    fn test_apply_many() {
+        for config in [
+            RandomSequenceBuilder::<u64>::new(0x12, 0x56),
+            RandomSequenceBuilder::<u64>::new(0x12, 0x56).with_len(1000),
+        ] {
+            let qpr = Qpr::new(&config, config.mask());
+            let mut values: Vec<u64> = (0..100).chain(config.mask() - 100..=config.mask()).collect();
+            let expected: Vec<u64> = values.iter().map(|&x| qpr.apply(x)).collect();
+            qpr.apply_many(&mut values);
+            assert_eq!(values, expected);
+        }
+    }
+
+    /// A user-supplied permutation, rotating and xoring within the domain, without an inverse.
+    #[derive(Debug, Clone)]
+    struct RotateXor {
+        width: u32,
+        xor: u32,
+    }
+
+    impl Permutation<u32> for RotateXor {
+        fn init(&mut self, _config: &RandomSequenceBuilder<u32>, mask: u32) {
+            self.width = mask.count_ones();
+            self.xor &= mask;
+        }
+
+        fn apply(&self, x: u32) -> u32 {
+            let mask = u32::MAX >> (32 - self.width);
+            ((x << 1 | x >> (self.width - 1)) & mask) ^ self.xor
+        }
+    }
+
+    #[test]
+    fn test_custom_permutation() {
+        let config = RandomSequenceBuilder::<u32>::new(0x12, 0x56).with_range(100..1100);
+        let mut sequence = config.into_iter_with(RotateXor { width: 0, xor: 0x5a5 });
+        assert_eq!(sequence.permutation().width, 10);
+
+        let nums: HashSet<u32> = sequence.clone().collect();
+        assert_eq!(nums, (100..1100).collect());
+        assert_eq!(sequence.len(), 1000);
+
+        // iteration, exhaustion, and batches work as with the default permutation
+        let mut out = [0; 1000];
+        assert_eq!(sequence.clone().fill(&mut out), 1000);
+        assert_eq!(out.to_vec(), sequence.clone().collect::<Vec<_>>());
+        sequence.set_index(999);
+        assert_eq!(sequence.next(), Some(sequence.n(999)));
+        assert!(sequence.exhausted());
+        assert_eq!(sequence.next(), None);
+        assert_eq!(sequence.clone().into_cursor().prev(), Some(sequence.n(998)));
+
+        // every shard is disjoint
+        let shards: Vec<u32> = sequence.split::<3>().into_iter().flatten().collect();
+        assert_eq!(shards, sequence.shard(0, 1).collect::<Vec<_>>());
+    }
+
+    #[test]
+    #[should_panic(expected = "RandomSequence permutation can't be inverted")]
+    fn test_custom_permutation_index_of() {
+        let sequence =
+            RandomSequenceBuilder::<u32>::new(0, 0).with_len(100).into_iter_with(RotateXor { width: 0, xor: 0 });
+        sequence.index_of(sequence.n(5));
+    }
+}
@@ -0,0 +1,354 @@
+//! Primality testing and the search for `prime = 3 mod 4` primes, to choose the prime of a sequence
+//! at runtime.
+//!
+//! Every function is a `const fn`, and allocation free.
+
+use crate::residue::Montgomery;
+
+/// The first 20 primes, used for trial division and as the Miller-Rabin witnesses.
+///
+/// The first 13 bases are proven to be deterministic for every `n < 3,317,044,064,679,887,385,961,981`,
+/// which is above `2^81` and so covers every `u64`. Above `u64`, the test is strengthened with a
+/// strong Lucas test, as fixed bases alone are not proven for the entire `u128` range.
+const BASES: [u128; 20] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71];
+
+/// Check whether `n` is prime, with the deterministic Miller-Rabin test.
+///
+/// The result is proven correct for every `n < 2^81`. Above `u64`, `n` must also pass the strong
+/// Lucas test, which with the base 2 Miller-Rabin test is the Baillie-PSW test, with no known
+/// composite passing both.
+///
+/// ```
+/// use rand_unique::primes::is_prime;
+///
+/// assert!(is_prime(18446744073709551557));
+/// assert!(!is_prime(3215031751));
+/// ```
+#[inline]
+pub const fn is_prime(n: u128) -> bool {
+    if n < 2 {
+        return false;
+    }
+
+    let mut i = 0;
+    while i < BASES.len() {
+        if n % BASES[i] == 0 {
+            return n == BASES[i];
+        }
+        i += 1;
+    }
+
+    // n is odd and larger than every base, which are all valid witnesses
+    match n <= u64::MAX as u128 {
+        true => miller_rabin_u64(n as u64),
+        false => miller_rabin_u128(n) && strong_lucas(n),
+    }
+}
+
+/// The largest prime `p < n` which satisfies `p = 3 mod 4`, or `None` if `n <= 3`.
+///
+/// These primes define the quadratic prime residue permutation of a sequence, where every integer
+/// `x < p` has a unique `x^2 % p` or `p - x^2 % p`.
+///
+/// ```
+/// use rand_unique::primes::largest_3_mod_4_prime_below;
+///
+/// assert_eq!(largest_3_mod_4_prime_below(1 << 16), Some(65519));
+/// assert_eq!(largest_3_mod_4_prime_below(3), None);
+/// ```
+#[inline]
+pub const fn largest_3_mod_4_prime_below(n: u128) -> Option<u128> {
+    if n <= 3 {
+        return None;
+    }
+
+    // the largest candidate below n with candidate = 3 mod 4
+    let mut candidate = n - 1;
+    candidate -= (candidate - 3) % 4;
+    while !is_prime(candidate) {
+        // 3 is the smallest candidate, and is prime
+        candidate -= 4;
+    }
+    Some(candidate)
+}
+
+macro_rules! miller_rabin {
+    ($name:ident, $type:ident) => {
+        /// The Miller-Rabin test of an odd `n` against every witness in [BASES], where `n` is larger
+        /// than every witness.
+        #[inline]
+        const fn $name(n: $type) -> bool {
+            let modulus = Montgomery::<$type>::new(n);
+
+            // n - 1 = d * 2^s, with d odd
+            let s = (n - 1).trailing_zeros();
+            let d = (n - 1) >> s;
+
+            let mut i = 0;
+            'witness: while i < BASES.len() {
+                let base = BASES[i] as $type;
+                i += 1;
+
+                // x = base^d % n
+                let mut x = 1;
+                let mut power = base;
+                let mut exp = d;
+                while exp > 0 {
+                    if exp & 1 == 1 {
+                        x = modulus.mul_mod(x, power);
+                    }
+                    power = modulus.mul_mod(power, power);
+                    exp >>= 1;
+                }
+
+                if x == 1 || x == n - 1 {
+                    continue;
+                }
+
+                // n is a strong probable prime to this base if x reaches n - 1 by squaring
+                let mut round = 1;
+                while round < s {
+                    x = modulus.mul_mod(x, x);
+                    if x == n - 1 {
+                        continue 'witness;
+                    }
+                    round += 1;
+                }
+                return false;
+            }
+            true
+        }
+    };
+}
+
+miller_rabin!(miller_rabin_u64, u64);
+miller_rabin!(miller_rabin_u128, u128);
+
+/// The strong Lucas probable prime test of an odd `n > 71`, with the parameters `P = 1` and
+/// `Q = (1 - D) / 4` for the first `D` of `5, -7, 9, -11, ...` with the Jacobi symbol `(D/n) = -1`.
+///
+/// The Lucas sequences are computed in Montgomery form.
+#[inline]
+const fn strong_lucas(n: u128) -> bool {
+    // no D exists for a perfect square
+    let root = isqrt(n);
+    if root * root == n {
+        return false;
+    }
+
+    let mut d: i128 = 5;
+    loop {
+        match jacobi(signed_mod(d, n), n) {
+            -1 => break,
+            // n shares a factor with D, which is smaller than n
+            0 => return false,
+            _ => d = -(d + 2 * d.signum()),
+        }
+    }
+    let q = (1 - d) / 4;
+
+    let modulus = Montgomery::<u128>::new(n);
+    let d = modulus.montgomery_form(signed_mod(d, n));
+    let q = modulus.montgomery_form(signed_mod(q, n));
+    let one = modulus.montgomery_form(1);
+
+    // n + 1 = k * 2^s, with k odd, where n + 1 doesn't overflow as n is odd
+    let s = (n + 1).trailing_zeros();
+    let k = (n + 1) >> s;
+
+    // U_k, V_k, and Q^k, starting from U_0 = 0, V_0 = 2, and Q^0 = 1
+    let (mut u, mut v, mut q_k) = (0, add_mod(one, one, n), one);
+    let mut bit = 128 - k.leading_zeros();
+    while bit > 0 {
+        bit -= 1;
+
+        // U_2j = U_j * V_j, V_2j = V_j^2 - 2 * Q^j, Q^2j = (Q^j)^2
+        u = modulus.mul_montgomery(u, v);
+        v = sub_mod(modulus.mul_montgomery(v, v), add_mod(q_k, q_k, n), n);
+        q_k = modulus.mul_montgomery(q_k, q_k);
+
+        // U_j+1 = (U_j + V_j) / 2, V_j+1 = (D * U_j + V_j) / 2, Q^j+1 = Q^j * Q
+        if (k >> bit) & 1 == 1 {
+            let next_u = half_mod(add_mod(u, v, n), n);
+            v = half_mod(add_mod(modulus.mul_montgomery(d, u), v, n), n);
+            u = next_u;
+            q_k = modulus.mul_montgomery(q_k, q);
+        }
+    }
+
+    // n is a strong Lucas probable prime if U_k = 0, or V_k * 2^r = 0 for some r < s
+    if u == 0 {
+        return true;
+    }
+    let mut round = 0;
+    while round < s {
+        if v == 0 {
+            return true;
+        }
+        v = sub_mod(modulus.mul_montgomery(v, v), add_mod(q_k, q_k, n), n);
+        q_k = modulus.mul_montgomery(q_k, q_k);
+        round += 1;
+    }
+    false
+}
+
+/// The Jacobi symbol `(a/n)` for an odd `n`.
+#[inline]
+const fn jacobi(mut a: u128, mut n: u128) -> i32 {
+    let mut result = 1;
+    a %= n;
+    while a != 0 {
+        while a % 2 == 0 {
+            a /= 2;
+            if n % 8 == 3 || n % 8 == 5 {
+                result = -result;
+            }
+        }
+        (a, n) = (n, a);
+        if a % 4 == 3 && n % 4 == 3 {
+            result = -result;
+        }
+        a %= n;
+    }
+    match n == 1 {
+        true => result,
+        false => 0,
+    }
+}
+
+/// The integer square root, `floor(sqrt(n))`.
+#[inline]
+const fn isqrt(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
+
+    // newton's method from above, starting at a power of two above the root
+    let mut x = 1 << ((128 - n.leading_zeros()) / 2 + 1);
+    loop {
+        let next = (x + n / x) / 2;
+        if next >= x {
+            return x;
+        }
+        x = next;
+    }
+}
+
+/// `x mod n` for a small signed `x`, in `[0, n)`.
+#[inline(always)]
+const fn signed_mod(x: i128, n: u128) -> u128 {
+    match x < 0 {
+        true => (n - x.unsigned_abs() % n) % n,
+        false => x as u128 % n,
+    }
+}
+
+/// `(a + b) % n` for `a, b < n`.
+#[inline(always)]
+const fn add_mod(a: u128, b: u128, n: u128) -> u128 {
+    match a.overflowing_add(b) {
+        (sum, true) => sum.wrapping_sub(n),
+        (sum, false) if sum >= n => sum - n,
+        (sum, false) => sum,
+    }
+}
+
+/// `(a - b) % n` for `a, b < n`.
+#[inline(always)]
+const fn sub_mod(a: u128, b: u128, n: u128) -> u128 {
+    match a >= b {
+        true => a - b,
+        false => a.wrapping_sub(b).wrapping_add(n),
+    }
+}
+
+/// `a / 2 % n` for `a < n` and an odd `n`, as `(a + n) / 2` for an odd `a`.
+#[inline(always)]
+const fn half_mod(a: u128, n: u128) -> u128 {
+    match a & 1 == 1 {
+        true => (a >> 1) + (n >> 1) + 1,
+        false => a >> 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::string::ToString;
+
+    use super::*;
+
+    #[test]
+    fn test_is_prime() {
+        for n in 0..10_000u128 {
+            assert_eq!(is_prime(n), is_prime::is_prime(&n.to_string()), "{}", n);
+        }
+
+        // the largest primes of each width, and their neighbours
+        for n in [
+            4294967291u128,
+            18446744073709551557,
+            170141183460469231731687303715884105727,
+            340282366920938463463374607431768211297,
+        ] {
+            assert!(is_prime(n), "{}", n);
+            assert!(!is_prime(n + 2), "{}", n + 2);
+            assert!(!is_prime(n - 2), "{}", n - 2);
+        }
+
+        // strong pseudoprimes to the smaller sets of bases
+        for n in [
+            2047u128,
+            1373653,
+            25326001,
+            3215031751,
+            2152302898747,
+            3474749660383,
+            341550071728321,
+            3825123056546413051,
+            318665857834031151167461,
+            3317044064679887385961981,
+        ] {
+            assert!(!is_prime(n), "{}", n);
+        }
+
+        // products of large primes
+        assert!(!is_prime(4294967291 * 4294967279));
+        assert!(!is_prime(18446744073709551557 * 18446744073709551533));
+        assert!(!is_prime(u128::MAX));
+    }
+
+    #[test]
+    fn test_strong_lucas() {
+        // the strong Lucas pseudoprimes are the only composites which pass
+        let pseudoprimes = [5459u128, 5777, 10877, 16109, 18971, 22499, 24569, 25199, 40309, 58519, 75077, 97439];
+        for n in (73..100_000u128).step_by(2) {
+            let expected = is_prime::is_prime(&n.to_string()) || pseudoprimes.contains(&n);
+            assert_eq!(strong_lucas(n), expected, "{}", n);
+        }
+
+        // the largest primes of u128, and composites above u64 with and without small factors
+        assert!(strong_lucas(340282366920938463463374607431768211297));
+        assert!(strong_lucas(340282366920938463463374607431768211283));
+        assert!(!strong_lucas(18446744073709551557 * 18446744073709551533));
+        assert!(!strong_lucas(18446744073709551557 * 18446744073709551557));
+        assert!(!strong_lucas(u128::MAX));
+    }
+
+    #[test]
+    fn test_largest_3_mod_4_prime_below() {
+        assert_eq!(largest_3_mod_4_prime_below(0), None);
+        assert_eq!(largest_3_mod_4_prime_below(3), None);
+        assert_eq!(largest_3_mod_4_prime_below(4), Some(3));
+        assert_eq!(largest_3_mod_4_prime_below(7), Some(3));
+        assert_eq!(largest_3_mod_4_prime_below(8), Some(7));
+        assert_eq!(largest_3_mod_4_prime_below(1000), Some(991));
+        assert_eq!(largest_3_mod_4_prime_below(u128::MAX), Some(340282366920938463463374607431768211283));
+
+        for n in 4..2_000u128 {
+            let prime = largest_3_mod_4_prime_below(n).unwrap();
+            assert!(prime < n && prime % 4 == 3);
+            assert!(is_prime::is_prime(&prime.to_string()), "{}", prime);
+            assert!((prime + 1..n).all(|p| p % 4 != 3 || !is_prime::is_prime(&p.to_string())));
+        }
+    }
+}
@@ -1,33 +1,56 @@
+use num_traits::{Bounded, NumCast, ToPrimitive};
 use rand::TryRng;
 
+use crate::builder::SequenceInt;
 use crate::{RandomSequence, RandomSequenceBuilder};
 
-macro_rules! init_rand {
-    ($type:ident, $tests:ident) => {
-        impl RandomSequenceBuilder<$type> {
-            /// Initialise a RandomSequenceBuilder from a random seed.
-            pub fn rand(rng: &mut impl TryRng) -> Self {
-                let seed_base = rng.try_next_u64().unwrap() as $type;
-                let seed_offset = rng.try_next_u64().unwrap() as $type;
-                Self::new(seed_base, seed_offset)
-            }
-        }
+/// A random seed, from one `u64` of the rng, or two for 128-bit types.
+#[inline]
+fn random_seed<T>(rng: &mut impl TryRng) -> T
+where
+    T: SequenceInt,
+{
+    let mut seed = rng.try_next_u64().unwrap() as u128;
+    if T::BITS > 64 {
+        seed = seed << 64 | rng.try_next_u64().unwrap() as u128;
+    }
+    let mask = T::Unsigned::max_value().to_u128().unwrap();
+    T::from_unsigned(NumCast::from(seed & mask).unwrap())
+}
 
-        impl RandomSequence<$type> {
-            /// Initialise a RandomSequence from a random seed.
-            pub fn rand(rng: &mut impl TryRng) -> Self {
-                RandomSequenceBuilder::<$type>::rand(rng).into_iter()
-            }
-        }
+impl<T> RandomSequenceBuilder<T>
+where
+    T: SequenceInt,
+{
+    /// Initialise a RandomSequenceBuilder from a random seed.
+    pub fn rand(rng: &mut impl TryRng) -> Self {
+        let seed_base = random_seed(rng);
+        let seed_offset = random_seed(rng);
+        T::builder(seed_base, seed_offset)
+    }
+}
+
+impl<T> RandomSequence<T>
+where
+    T: SequenceInt,
+{
+    /// Initialise a RandomSequence from a random seed.
+    pub fn rand(rng: &mut impl TryRng) -> Self {
+        RandomSequenceBuilder::<T>::rand(rng).into_iter()
+    }
+}
 
-        #[cfg(test)]
-        mod $tests {
-            use rand::rngs::SysRng;
+#[cfg(test)]
+mod tests {
+    use rand::rngs::{StdRng, SysRng};
+    use rand::{Rng, SeedableRng};
 
-            use super::*;
+    use super::*;
 
+    macro_rules! test_rand {
+        ($name:ident, $type:ident) => {
             #[test]
-            fn test_rand() {
+            fn $name() {
                 let mut rng = SysRng;
                 let config1 = RandomSequenceBuilder::<$type>::rand(&mut rng);
                 let config2 = RandomSequenceBuilder::<$type>::rand(&mut rng);
@@ -36,12 +59,34 @@ macro_rules! init_rand {
                 let mut sequence = RandomSequence::<$type>::rand(&mut rng);
                 assert_ne!(sequence.next(), sequence.next());
             }
-        }
-    };
-}
+        };
+    }
+
+    test_rand!(test_u8_rand, u8);
+    test_rand!(test_u16_rand, u16);
+    test_rand!(test_u32_rand, u32);
+    test_rand!(test_u64_rand, u64);
+    test_rand!(test_u128_rand, u128);
+    test_rand!(test_usize_rand, usize);
+    test_rand!(test_i8_rand, i8);
+    test_rand!(test_i16_rand, i16);
+    test_rand!(test_i32_rand, i32);
+    test_rand!(test_i64_rand, i64);
+    test_rand!(test_i128_rand, i128);
+    test_rand!(test_isize_rand, isize);
 
-init_rand!(u8, tests_u8);
-init_rand!(u16, tests_u16);
-init_rand!(u32, tests_u32);
-init_rand!(u64, tests_u64);
-init_rand!(usize, tests_usize);
+    #[test]
+    fn test_rand_seeds() {
+        // the seeds are the truncated outputs of the rng, with the high half first for 128-bit types
+        let mut rng = StdRng::seed_from_u64(7);
+        let (a, b, c, d) = (rng.next_u64(), rng.next_u64(), rng.next_u64(), rng.next_u64());
+
+        let config = RandomSequenceBuilder::<u16>::rand(&mut StdRng::seed_from_u64(7));
+        assert_eq!((config.seed_base, config.seed_offset), (a as u16, b as u16));
+        let config = RandomSequenceBuilder::<i64>::rand(&mut StdRng::seed_from_u64(7));
+        assert_eq!((config.seed_base, config.seed_offset), (a as i64, b as i64));
+        let config = RandomSequenceBuilder::<u128>::rand(&mut StdRng::seed_from_u64(7));
+        assert_eq!(config.seed_base, (a as u128) << 64 | b as u128);
+        assert_eq!(config.seed_offset, (c as u128) << 64 | d as u128);
+    }
+}
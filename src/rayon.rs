@@ -0,0 +1,324 @@
+use core::fmt::{Debug, Formatter};
+
+use num_traits::{NumCast, WrappingAdd};
+use rayon::iter::plumbing::{bridge, Consumer, Producer, ProducerCallback, UnindexedConsumer};
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+
+use crate::builder::{RandomSequenceBuilder, SequenceInt};
+use crate::permutation::{Permutation, Qpr};
+use crate::sequence::RandomSequence;
+
+/// A parallel iterator over the remaining elements of a [RandomSequence], created by
+/// `RandomSequence::into_par_iter`. Must have the `rayon` feature enabled.
+///
+/// As computing any element is O(1), the sequence is split between threads by index.
+#[derive(Clone)]
+pub struct ParIter<T, P = Qpr<T>>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+    /// The sequence, with `current_index` as the first element.
+    sequence: RandomSequence<T, P>,
+
+    /// The number of remaining elements.
+    len: usize,
+}
+
+impl<T, P> Debug for ParIter<T, P>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+    RandomSequence<T, P>: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ParIter").field("sequence", &self.sequence).field("len", &self.len).finish()
+    }
+}
+
+impl<T, P> IntoParallelIterator for RandomSequence<T, P>
+where
+    T: SequenceInt + Send,
+    T::Unsigned: Send,
+    P: Permutation<T>,
+    RandomSequence<T, P>: Send + Sync,
+{
+    type Iter = ParIter<T, P>;
+    type Item = T;
+
+    /// Iterate over the remaining elements of the sequence in parallel, starting from the current
+    /// index. An exhausted sequence has no remaining elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of remaining elements does not fit in `usize`, such as for a full
+    /// `RandomSequence<u64>`. Use [RandomSequenceBuilder::with_len] to bound the sequence.
+    #[inline]
+    fn into_par_iter(self) -> Self::Iter {
+        let len = self.remaining().expect("RandomSequence is too long for a parallel iterator");
+        ParIter { sequence: self, len }
+    }
+}
+
+impl<T> IntoParallelIterator for RandomSequenceBuilder<T>
+where
+    T: SequenceInt + Send,
+    T::Unsigned: Send,
+    RandomSequence<T>: Send + Sync,
+{
+    type Iter = ParIter<T>;
+    type Item = T;
+
+    /// Iterate over every element of the sequence in parallel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of the sequence does not fit in `usize`, such as for a full
+    /// `RandomSequence<u64>`. Use [RandomSequenceBuilder::with_len] to bound the sequence.
+    #[inline]
+    fn into_par_iter(self) -> Self::Iter {
+        self.into_iter().into_par_iter()
+    }
+}
+
+impl<T, P> ParallelIterator for ParIter<T, P>
+where
+    T: SequenceInt + Send,
+    T::Unsigned: Send,
+    P: Permutation<T>,
+    RandomSequence<T, P>: Send + Sync,
+{
+    type Item = T;
+
+    #[inline]
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        bridge(self, consumer)
+    }
+
+    #[inline]
+    fn opt_len(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+impl<T, P> IndexedParallelIterator for ParIter<T, P>
+where
+    T: SequenceInt + Send,
+    T::Unsigned: Send,
+    P: Permutation<T>,
+    RandomSequence<T, P>: Send + Sync,
+{
+    #[inline]
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    fn drive<C>(self, consumer: C) -> C::Result
+    where
+        C: Consumer<Self::Item>,
+    {
+        bridge(self, consumer)
+    }
+
+    #[inline]
+    fn with_producer<CB>(self, callback: CB) -> CB::Output
+    where
+        CB: ProducerCallback<Self::Item>,
+    {
+        callback.callback(SequenceProducer {
+            sequence: &self.sequence,
+            start: self.sequence.current_index,
+            len: self.len,
+        })
+    }
+}
+
+/// Produces the `len` elements of the sequence from index `start`, and splits them between threads.
+struct SequenceProducer<'a, T, P>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+    sequence: &'a RandomSequence<T, P>,
+    start: T::Unsigned,
+    len: usize,
+}
+
+impl<T, P> SequenceProducer<'_, T, P>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+    /// The index `offset` elements after `start`, for `offset <= len`.
+    #[inline(always)]
+    fn offset(&self, offset: usize) -> T::Unsigned {
+        // the only offset that doesn't fit is the length of a sequence covering the entire type, which
+        // wraps around to start
+        match <T::Unsigned as NumCast>::from(offset) {
+            Some(offset) => self.start.wrapping_add(&offset),
+            None => self.start,
+        }
+    }
+}
+
+impl<'a, T, P> Producer for SequenceProducer<'a, T, P>
+where
+    T: SequenceInt + Send,
+    T::Unsigned: Send,
+    P: Permutation<T>,
+    RandomSequence<T, P>: Sync,
+{
+    type Item = T;
+    type IntoIter = Self;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self
+    }
+
+    #[inline]
+    fn split_at(self, index: usize) -> (Self, Self) {
+        let right = SequenceProducer {
+            sequence: self.sequence,
+            start: self.offset(index),
+            len: self.len - index,
+        };
+        let left = SequenceProducer {
+            sequence: self.sequence,
+            start: self.start,
+            len: index,
+        };
+        (left, right)
+    }
+}
+
+impl<T, P> Iterator for SequenceProducer<'_, T, P>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        let next = self.sequence.n_unsigned(self.start);
+        self.start = self.offset(1);
+        self.len -= 1;
+        Some(next)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T, P> DoubleEndedIterator for SequenceProducer<'_, T, P>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        Some(self.sequence.n_unsigned(self.offset(self.len)))
+    }
+}
+
+impl<T, P> ExactSizeIterator for SequenceProducer<'_, T, P>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+
+    use crate::{RandomSequence, RandomSequenceBuilder};
+
+    macro_rules! test_par_iter {
+        ($name:ident, $type:ident) => {
+            #[test]
+            fn $name() {
+                for config in [
+                    RandomSequenceBuilder::<$type>::new(0x12, 0x56).with_len(1),
+                    RandomSequenceBuilder::<$type>::new(0x12, 0x56).with_len(100),
+                    RandomSequenceBuilder::<$type>::new(0x12, 0x56).with_len(($type::MAX as u128).min(10_000) as $type),
+                ] {
+                    let expected: Vec<$type> = config.into_iter().collect();
+                    let nums: Vec<$type> = config.into_par_iter().collect();
+                    assert_eq!(nums, expected);
+
+                    // respects the current index
+                    let mut sequence = config.into_iter();
+                    sequence.nth(expected.len() / 2);
+                    let remaining: Vec<$type> = sequence.clone().collect();
+                    assert_eq!(sequence.clone().into_par_iter().len(), remaining.len());
+                    let nums: Vec<$type> = sequence.into_par_iter().collect();
+                    assert_eq!(nums, remaining);
+
+                    // respects the shard boundaries
+                    let shard = config.into_iter().shard(1, 3);
+                    let nums: Vec<$type> = shard.clone().into_par_iter().collect();
+                    assert_eq!(nums, shard.collect::<Vec<_>>());
+
+                    // indexed operations
+                    let nums: Vec<$type> = config.into_par_iter().rev().collect();
+                    assert_eq!(nums, expected.iter().rev().copied().collect::<Vec<_>>());
+                    let nums: Vec<$type> = config.into_par_iter().skip(1).step_by(3).collect();
+                    assert_eq!(nums, expected.iter().skip(1).step_by(3).copied().collect::<Vec<_>>());
+                }
+            }
+        };
+    }
+
+    test_par_iter!(test_u8_par_iter, u8);
+    test_par_iter!(test_u16_par_iter, u16);
+    test_par_iter!(test_u32_par_iter, u32);
+    test_par_iter!(test_u64_par_iter, u64);
+    test_par_iter!(test_u128_par_iter, u128);
+    test_par_iter!(test_usize_par_iter, usize);
+    test_par_iter!(test_i16_par_iter, i16);
+    test_par_iter!(test_i64_par_iter, i64);
+
+    #[test]
+    fn test_par_iter_full() {
+        let expected: Vec<u8> = RandomSequence::<u8>::new(0x12, 0x56).collect();
+        let nums: Vec<u8> = RandomSequence::<u8>::new(0x12, 0x56).into_par_iter().collect();
+        assert_eq!(nums.len(), 256);
+        assert_eq!(nums, expected);
+        let nums: Vec<u8> = RandomSequence::<u8>::new(0x12, 0x56).into_par_iter().rev().collect();
+        assert_eq!(nums, expected.into_iter().rev().collect::<Vec<_>>());
+
+        let nums: Vec<i16> = RandomSequence::<i16>::new(0x12, 0x56).into_par_iter().collect();
+        assert_eq!(nums.len(), 65536);
+    }
+
+    #[test]
+    fn test_par_iter_exhausted() {
+        let mut sequence = RandomSequenceBuilder::<u32>::new(0, 0).with_len(10).into_iter();
+        sequence.by_ref().for_each(drop);
+        assert!(sequence.exhausted());
+        assert_eq!(sequence.into_par_iter().count(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "RandomSequence is too long for a parallel iterator")]
+    fn test_par_iter_too_long() {
+        RandomSequence::<u64>::new(0, 0).into_par_iter();
+    }
+}
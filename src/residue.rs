@@ -0,0 +1,385 @@
+use core::fmt::Debug;
+
+use num_traits::{AsPrimitive, PrimInt, WrappingAdd, WrappingSub};
+
+/// Modular arithmetic against a prime, used to compute the quadratic prime residue.
+pub trait QuadraticResidue
+where
+    Self: PrimInt + AsPrimitive<usize> + WrappingAdd + WrappingSub,
+{
+    /// Constants precomputed from the prime, to avoid division when computing residues.
+    type Modulus: Copy + Debug + Eq;
+
+    /// Precompute the [QuadraticResidue::Modulus] for an odd prime.
+    fn modulus(prime: Self) -> Self::Modulus;
+
+    /// Compute the quadratic residue of this number, `(self * self) % prime`.
+    #[inline(always)]
+    fn residue(self, modulus: &Self::Modulus) -> Self {
+        self.mul_mod(self, modulus)
+    }
+
+    /// Compute `(self * other) % prime` without overflowing, for `self, other < prime`.
+    fn mul_mod(self, other: Self, modulus: &Self::Modulus) -> Self;
+
+    /// Convert `self < prime` into the form that [QuadraticResidue::mul_form] multiplies in, such as
+    /// Montgomery form. The identity by default.
+    #[inline(always)]
+    fn into_form(self, _modulus: &Self::Modulus) -> Self {
+        self
+    }
+
+    /// Convert back from the form of [QuadraticResidue::into_form]. The identity by default.
+    #[inline(always)]
+    fn out_of_form(self, _modulus: &Self::Modulus) -> Self {
+        self
+    }
+
+    /// Compute the product of two values in the form of [QuadraticResidue::into_form], in that same
+    /// form. The same as [QuadraticResidue::mul_mod] by default.
+    #[inline(always)]
+    fn mul_form(self, other: Self, modulus: &Self::Modulus) -> Self {
+        self.mul_mod(other, modulus)
+    }
+
+    /// Compute `self.pow(exp) % prime` without overflowing, for `self < prime`.
+    ///
+    /// The intermediate values are kept in the form of [QuadraticResidue::into_form], so that each
+    /// multiplication needs only a single reduction.
+    #[inline]
+    fn pow_mod(self, mut exp: Self, modulus: &Self::Modulus) -> Self {
+        let mut base = self.into_form(modulus);
+        let mut result = Self::one().into_form(modulus);
+        while !exp.is_zero() {
+            if exp & Self::one() == Self::one() {
+                result = result.mul_form(base, modulus);
+            }
+            base = base.mul_form(base, modulus);
+            exp = exp >> 1;
+        }
+        result.out_of_form(modulus)
+    }
+}
+
+/// The modulus for residues computed with a division in the next larger type, which is fast enough
+/// for small types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Division<T> {
+    /// The prime.
+    prime: T,
+}
+
+macro_rules! impl_division {
+    ($base_type:ident, $larger_type:ident) => {
+        impl Division<$base_type> {
+            #[inline(always)]
+            pub const fn new(prime: $base_type) -> Self {
+                Self { prime }
+            }
+
+            #[inline(always)]
+            pub const fn mul_mod(&self, a: $base_type, b: $base_type) -> $base_type {
+                ((a as $larger_type * b as $larger_type) % self.prime as $larger_type) as $base_type
+            }
+        }
+    };
+}
+
+/// Implement [QuadraticResidue] with a modulus type providing const `new` and `mul_mod` methods,
+/// which are also usable to compute sequences in const contexts. Montgomery moduli also multiply in
+/// Montgomery form.
+macro_rules! impl_residue {
+    ($base_type:ident, Montgomery) => {
+        impl QuadraticResidue for $base_type {
+            type Modulus = Montgomery<$base_type>;
+
+            #[inline(always)]
+            fn modulus(prime: Self) -> Self::Modulus {
+                Montgomery::<$base_type>::new(prime)
+            }
+
+            #[inline(always)]
+            fn mul_mod(self, other: Self, modulus: &Self::Modulus) -> Self {
+                modulus.mul_mod(self, other)
+            }
+
+            #[inline(always)]
+            fn into_form(self, modulus: &Self::Modulus) -> Self {
+                modulus.montgomery_form(self)
+            }
+
+            #[inline(always)]
+            fn out_of_form(self, modulus: &Self::Modulus) -> Self {
+                modulus.normal_form(self)
+            }
+
+            #[inline(always)]
+            fn mul_form(self, other: Self, modulus: &Self::Modulus) -> Self {
+                modulus.mul_montgomery(self, other)
+            }
+        }
+    };
+    ($base_type:ident, $modulus:ty) => {
+        impl QuadraticResidue for $base_type {
+            type Modulus = $modulus;
+
+            #[inline(always)]
+            fn modulus(prime: Self) -> Self::Modulus {
+                <$modulus>::new(prime)
+            }
+
+            #[inline(always)]
+            fn mul_mod(self, other: Self, modulus: &Self::Modulus) -> Self {
+                modulus.mul_mod(self, other)
+            }
+        }
+    };
+}
+
+/// The constants for Montgomery multiplication, which replaces the division in `x % prime` with
+/// multiplications and shifts.
+///
+/// Montgomery reduction computes `redc(x) = x * R^-1 % prime` for `R = 2^bits`. A value `a` in
+/// Montgomery form is `a * R % prime`, and the product of two values in Montgomery form stays in
+/// Montgomery form with a single reduction, `redc(aR * bR) = abR`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Montgomery<T> {
+    /// The odd prime.
+    prime: T,
+
+    /// `prime^-1 % R`.
+    inverse: T,
+
+    /// `R^2 % prime`.
+    r2: T,
+}
+
+macro_rules! impl_montgomery {
+    ($base_type:ident, $larger_type:ident) => {
+        impl Montgomery<$base_type> {
+            /// Compute the full product, returned as `(high, low)`.
+            #[inline(always)]
+            const fn mul_wide(a: $base_type, b: $base_type) -> ($base_type, $base_type) {
+                let product = a as $larger_type * b as $larger_type;
+                ((product >> $base_type::BITS) as $base_type, product as $base_type)
+            }
+        }
+
+        impl_montgomery!($base_type);
+    };
+    ($base_type:ident) => {
+        impl Montgomery<$base_type> {
+            #[inline]
+            pub const fn new(prime: $base_type) -> Self {
+                assert!(prime & 1 == 1, "Montgomery modulus must be odd");
+
+                // newton's method doubles the correct bits of the inverse on each iteration, starting
+                // from 3 bits as prime * prime = 1 mod 8 for all odd primes, so log2(bits) iterations
+                // are always enough
+                let mut inverse = prime;
+                let mut step = 0;
+                while step < $base_type::BITS.ilog2() {
+                    inverse = inverse.wrapping_mul((2 as $base_type).wrapping_sub(prime.wrapping_mul(inverse)));
+                    step += 1;
+                }
+
+                // R % prime = (R - prime) % prime, then double it bits times to get R^2 % prime
+                let mut r2 = prime.wrapping_neg() % prime;
+                let mut bit = 0;
+                while bit < $base_type::BITS {
+                    r2 = match r2.checked_add(r2) {
+                        Some(double) if double < prime => double,
+                        _ => r2.wrapping_add(r2).wrapping_sub(prime),
+                    };
+                    bit += 1;
+                }
+
+                Self { prime, inverse, r2 }
+            }
+
+            /// Montgomery reduction, `(high * R + low) * R^-1 % prime` for `high < prime`.
+            #[inline(always)]
+            const fn redc(&self, high: $base_type, low: $base_type) -> $base_type {
+                let m = low.wrapping_mul(self.inverse);
+                let (m_high, _) = Self::mul_wide(m, self.prime);
+
+                // the low halves of x and m * prime are equal, and so cancel out
+                let (reduced, borrow) = high.overflowing_sub(m_high);
+                match borrow {
+                    true => reduced.wrapping_add(self.prime),
+                    false => reduced,
+                }
+            }
+
+            /// Convert `a < prime` into Montgomery form, `a * R % prime`.
+            #[inline(always)]
+            pub const fn montgomery_form(&self, a: $base_type) -> $base_type {
+                let (high, low) = Self::mul_wide(a, self.r2);
+                self.redc(high, low)
+            }
+
+            /// Convert `a` out of Montgomery form, `a * R^-1 % prime`.
+            #[inline(always)]
+            pub const fn normal_form(&self, a: $base_type) -> $base_type {
+                self.redc(0, a)
+            }
+
+            /// Multiply two values in Montgomery form, keeping the product in Montgomery form.
+            #[inline(always)]
+            pub const fn mul_montgomery(&self, a: $base_type, b: $base_type) -> $base_type {
+                let (high, low) = Self::mul_wide(a, b);
+                self.redc(high, low)
+            }
+
+            /// Only one of the operands needs converting into Montgomery form, as `redc(aR * b) = ab`.
+            #[inline(always)]
+            pub const fn mul_mod(&self, a: $base_type, b: $base_type) -> $base_type {
+                self.mul_montgomery(self.montgomery_form(a), b)
+            }
+        }
+    };
+}
+
+impl_division!(u8, u16);
+impl_division!(u16, u32);
+// only compared against in the benches and tests
+#[cfg(any(test, feature = "bench"))]
+impl_division!(u32, u64);
+#[cfg(any(test, feature = "bench"))]
+impl_division!(u64, u128);
+impl_montgomery!(u32, u64);
+impl_montgomery!(u64, u128);
+impl_montgomery!(u128);
+#[cfg(target_pointer_width = "64")]
+impl_montgomery!(usize, u128);
+#[cfg(target_pointer_width = "32")]
+impl_montgomery!(usize, u64);
+#[cfg(not(any(target_pointer_width = "64", target_pointer_width = "32")))]
+compile_error!("Unsupported pointer width.");
+
+impl_residue!(u8, Division<u8>);
+impl_residue!(u16, Division<u16>);
+impl_residue!(u32, Montgomery);
+impl_residue!(u64, Montgomery);
+impl_residue!(u128, Montgomery);
+impl_residue!(usize, Montgomery);
+
+/// There is no larger type for u128, so the 256-bit product is computed from 64-bit limbs.
+impl Montgomery<u128> {
+    /// Compute the full 256-bit product of two u128s, returned as `(high, low)`.
+    #[inline(always)]
+    const fn mul_wide(a: u128, b: u128) -> (u128, u128) {
+        const LOW: u128 = u64::MAX as u128;
+        let (a_high, a_low) = (a >> 64, a & LOW);
+        let (b_high, b_low) = (b >> 64, b & LOW);
+
+        let low_low = a_low * b_low;
+        let low_high = a_low * b_high;
+        let high_low = a_high * b_low;
+        let high_high = a_high * b_high;
+
+        let middle = (low_low >> 64) + (low_high & LOW) + (high_low & LOW);
+        let low = (middle << 64) | (low_low & LOW);
+        let high = high_high + (low_high >> 64) + (high_low >> 64) + (middle >> 64);
+        (high, low)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reference `(a * b) % prime`, using repeated doubling.
+    fn reference_mul_mod(a: u128, mut b: u128, prime: u128) -> u128 {
+        let mut result = 0u128;
+        let mut a = a % prime;
+        while b > 0 {
+            if b & 1 == 1 {
+                result = add_mod(result, a, prime);
+            }
+            a = add_mod(a, a, prime);
+            b >>= 1;
+        }
+        result
+    }
+
+    /// Reference `a.pow(exp) % prime`, using repeated squaring.
+    fn reference_pow_mod(a: u128, mut exp: u128, prime: u128) -> u128 {
+        let mut result = 1 % prime;
+        let mut base = a % prime;
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = reference_mul_mod(result, base, prime);
+            }
+            base = reference_mul_mod(base, base, prime);
+            exp >>= 1;
+        }
+        result
+    }
+
+    fn add_mod(a: u128, b: u128, prime: u128) -> u128 {
+        match a.checked_add(b) {
+            Some(sum) => sum % prime,
+            None => a.wrapping_add(b).wrapping_sub(prime),
+        }
+    }
+
+    macro_rules! test_mul_mod {
+        ($name:ident, $type:ident, $primes:expr) => {
+            #[test]
+            fn $name() {
+                for prime in $primes {
+                    let modulus = $type::modulus(prime);
+                    let half = prime >> 1;
+                    for a in [0, 1, 2, 3, half, half + 1, prime - 2, prime - 1] {
+                        for b in [0, 1, 2, 3, half, half + 1, prime - 2, prime - 1] {
+                            let expected = reference_mul_mod(a as u128, b as u128, prime as u128) as $type;
+                            assert_eq!(a.mul_mod(b, &modulus), expected, "{} * {} % {}", a, b, prime);
+                            let expected = reference_pow_mod(a as u128, b as u128, prime as u128) as $type;
+                            assert_eq!(a.pow_mod(b, &modulus), expected, "{} ^ {} % {}", a, b, prime);
+                        }
+                        assert_eq!(a.residue(&modulus), a.mul_mod(a, &modulus));
+                    }
+                }
+            }
+        };
+    }
+
+    test_mul_mod!(test_u8_mul_mod, u8, [3u8, 7, 11, 251]);
+    test_mul_mod!(test_u16_mul_mod, u16, [3u16, 251, 65519]);
+    test_mul_mod!(test_u32_mul_mod, u32, [3u32, 251, 65519, 2147483647, 4294967291]);
+    test_mul_mod!(test_u64_mul_mod, u64, [3u64, 4294967291, 9223372036854775783, 18446744073709551427]);
+    test_mul_mod!(test_usize_mul_mod, usize, [3usize, 251, 4294967291]);
+    test_mul_mod!(
+        test_u128_mul_mod,
+        u128,
+        [3u128, 18446744073709551427, 170141183460469231731687303715884105727, 340282366920938463463374607431768211283]
+    );
+
+    #[test]
+    fn test_moduli() {
+        // the moduli which aren't used by the sequences, but are compared against in the benches
+        let (division, montgomery) = (Division::<u32>::new(4294967291), Montgomery::<u32>::new(4294967291));
+        for (a, b) in [(0u32, 5u32), (1, 4294967290), (4294967290, 4294967290), (123456789, 987654321)] {
+            let expected = reference_mul_mod(a as u128, b as u128, 4294967291) as u32;
+            assert_eq!(division.mul_mod(a, b), expected);
+            assert_eq!(montgomery.mul_mod(a, b), expected);
+        }
+        let (division, montgomery) =
+            (Division::<u64>::new(18446744073709551427), Montgomery::<u64>::new(18446744073709551427));
+        assert_eq!(division.mul_mod(u64::MAX - 189, 3), montgomery.mul_mod(u64::MAX - 189, 3));
+    }
+
+    #[test]
+    #[should_panic(expected = "Montgomery modulus must be odd")]
+    fn test_montgomery_even() {
+        Montgomery::<u64>::new(4);
+    }
+
+    #[test]
+    fn test_mul_wide() {
+        assert_eq!(Montgomery::<u128>::mul_wide(u128::MAX, u128::MAX), (u128::MAX - 1, 1));
+        assert_eq!(Montgomery::<u128>::mul_wide(1 << 64, 1 << 64), (1, 0));
+        assert_eq!(Montgomery::<u64>::mul_wide(u64::MAX, u64::MAX), (u64::MAX - 1, 1));
+    }
+}
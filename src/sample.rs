@@ -0,0 +1,261 @@
+//! Random sampling without replacement, to pick `amount` distinct values from `[0, length)`.
+//!
+//! A [Sample] is the first `amount` outputs of a [RandomSequence] bounded to `length` values, and so
+//! is computed lazily in O(1) memory for any `length` up to `u64::MAX`, without allocating.
+//!
+//! [sample_rng] mirrors [`rand::seq::index::sample`](https://docs.rs/rand/latest/rand/seq/index/fn.sample.html),
+//! and [Sample] the methods of its `IndexVec`, so that it can be swapped in:
+//!
+//! ```
+//! use rand_unique::sample::sample;
+//!
+//! let picked = sample(1_000_000_000_000, 5, 42);
+//! assert_eq!(picked.len(), 5);
+//! assert!(picked.iter().all(|value| value < 1_000_000_000_000));
+//! assert_eq!(picked.index(2), picked.iter().nth(2).unwrap());
+//! ```
+
+use core::fmt::{Debug, Formatter};
+
+use crate::builder::{RandomSequenceBuilder, SequenceInt};
+use crate::sequence::RandomSequence;
+
+/// `amount` distinct values from `[0, length)` in a random order, computed lazily.
+///
+/// The equivalent of rand's `IndexVec`, with [Sample::len], [Sample::index], [Sample::iter], and
+/// `into_vec` with the `std` feature.
+#[derive(Clone)]
+pub struct Sample<T>
+where
+    T: SequenceInt,
+{
+    /// The sequence over `[0, length)`, bounded to its first `amount` indices.
+    sequence: RandomSequence<T>,
+
+    /// The number of values in the sample.
+    amount: T,
+}
+
+/// Sample `amount` distinct values from `[0, length)`, deterministically for each `seed`.
+///
+/// The sequence is [RandomSequenceBuilder::from_key] of the little endian bytes of the seed, and so
+/// the sample is the same on every platform.
+///
+/// # Panics
+///
+/// Panics if `amount > length`.
+#[inline]
+pub fn sample(length: u64, amount: u64, seed: u64) -> Sample<u64> {
+    Sample::new(RandomSequenceBuilder::<u64>::from_key(&seed.to_le_bytes()), length, amount)
+}
+
+/// Randomly sample `amount` distinct values from `[0, length)`, a drop-in replacement for
+/// `rand::seq::index::sample` that doesn't allocate.
+///
+/// # Panics
+///
+/// Panics if `amount > length`.
+#[cfg(feature = "rand")]
+#[inline]
+pub fn sample_rng<R>(rng: &mut R, length: usize, amount: usize) -> Sample<usize>
+where
+    R: rand::Rng + ?Sized,
+{
+    let config = RandomSequenceBuilder::<usize>::new(rng.next_u64() as usize, rng.next_u64() as usize);
+    Sample::new(config, length, amount)
+}
+
+impl<T> Sample<T>
+where
+    T: SequenceInt,
+{
+    /// Take the first `amount` outputs of the sequence from `config`, bounded to `length`.
+    #[inline]
+    fn new(config: RandomSequenceBuilder<T>, length: T, amount: T) -> Self {
+        assert!(amount <= length, "`amount` of samples must be less than or equal to `length`");
+
+        // an empty sample still needs a valid sequence, which is already exhausted
+        let config = config.with_len(length.max(T::one()));
+        let mut sequence = config.into_iter();
+        sequence.last_index = (amount.max(T::one()) - T::one()).to_unsigned();
+        sequence.back_index = sequence.last_index;
+        sequence.ended = amount.is_zero();
+        Self { sequence, amount }
+    }
+
+    /// The number of values in the sample.
+    #[inline]
+    pub fn len(&self) -> T {
+        self.amount
+    }
+
+    /// Check whether the sample is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.amount.is_zero()
+    }
+
+    /// Get the value at position `index` of the sample, in O(1).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index >= len()`.
+    #[inline]
+    pub fn index(&self, index: T) -> T {
+        assert!(index >= T::zero() && index < self.amount, "Sample index out of bounds");
+        self.sequence.n(index)
+    }
+
+    /// Check whether `value` is in the sample, in O(1).
+    #[inline]
+    pub fn contains(&self, value: T) -> bool {
+        self.sequence.contains_before(value, self.amount)
+    }
+
+    /// Iterate over the values of the sample, in order.
+    #[inline]
+    pub fn iter(&self) -> RandomSequence<T> {
+        self.sequence.clone()
+    }
+
+    /// Collect the values of the sample.
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn into_vec(self) -> std::vec::Vec<T> {
+        self.sequence.collect()
+    }
+}
+
+impl<T> Debug for Sample<T>
+where
+    T: SequenceInt + Debug,
+    RandomSequence<T>: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Sample").field("sequence", &self.sequence).field("amount", &self.amount).finish()
+    }
+}
+
+impl<T> IntoIterator for Sample<T>
+where
+    T: SequenceInt,
+{
+    type Item = T;
+    type IntoIter = RandomSequence<T>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.sequence
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::vec::Vec;
+
+    use super::*;
+
+    #[test]
+    fn test_sample() {
+        for (length, amount) in [(1, 1), (10, 3), (10, 10), (1000, 1), (1000, 999), (12345, 500)] {
+            let picked = sample(length, amount, 7);
+            assert_eq!(picked.len(), amount);
+            assert!(!picked.is_empty());
+
+            let values: Vec<u64> = picked.iter().collect();
+            assert_eq!(values.len() as u64, amount);
+            assert!(values.iter().all(|&value| value < length));
+            assert_eq!(values.iter().collect::<HashSet<_>>().len() as u64, amount);
+
+            for (i, &value) in values.iter().enumerate() {
+                assert_eq!(picked.index(i as u64), value);
+                assert!(picked.contains(value));
+            }
+            let found = (0..length).filter(|value| picked.contains(*value)).count();
+            assert_eq!(found as u64, amount);
+
+            assert_eq!(picked.into_iter().collect::<Vec<_>>(), values);
+        }
+    }
+
+    #[test]
+    fn test_sample_full() {
+        let mut values = sample(100, 100, 3).iter().collect::<Vec<_>>();
+        values.sort_unstable();
+        assert_eq!(values, (0..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_sample_empty() {
+        for length in [0, 1, 100, u64::MAX] {
+            let picked = sample(length, 0, 1);
+            assert!(picked.is_empty());
+            assert_eq!(picked.len(), 0);
+            assert_eq!(picked.iter().next(), None);
+            assert!(!picked.contains(0));
+        }
+    }
+
+    #[test]
+    fn test_sample_large() {
+        let picked = sample(u64::MAX, 1000, 11);
+        let values: HashSet<u64> = picked.iter().collect();
+        assert_eq!(values.len(), 1000);
+        assert!(values.iter().all(|&value| value < u64::MAX));
+        assert!(values.iter().all(|&value| picked.contains(value)));
+        assert!(!picked.contains(u64::MAX));
+
+        let picked = sample(1 << 40, 5, 11);
+        assert!(picked.iter().all(|value| value < 1 << 40));
+    }
+
+    #[test]
+    fn test_sample_seed() {
+        let a = sample(1_000_000, 20, 1).iter().collect::<Vec<_>>();
+        assert_eq!(a, sample(1_000_000, 20, 1).iter().collect::<Vec<_>>());
+        assert_ne!(a, sample(1_000_000, 20, 2).iter().collect::<Vec<_>>());
+
+        // a smaller sample is a prefix of a larger one from the same seed
+        assert_eq!(sample(1_000_000, 5, 1).iter().collect::<Vec<_>>(), a[..5]);
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_sample_rng() {
+        let mut rng = <rand::rngs::StdRng as rand::SeedableRng>::seed_from_u64(42);
+        let picked = sample_rng(&mut rng, 500, 50);
+        let values: HashSet<usize> = picked.iter().collect();
+        assert_eq!(values.len(), 50);
+        assert!(values.iter().all(|&value| value < 500));
+
+        // swapped in for rand::seq::index::sample
+        let reference = rand::seq::index::sample(&mut rng, 500, 50);
+        let picked = sample_rng(&mut rng, 500, 50);
+        assert_eq!(picked.len(), reference.len());
+        assert_eq!(picked.is_empty(), reference.is_empty());
+        assert!(picked.into_iter().all(|value| value < 500));
+
+        let dyn_rng: &mut dyn rand::Rng = &mut rng;
+        assert_eq!(sample_rng(dyn_rng, 10, 10).len(), 10);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_into_vec() {
+        let picked = sample(1000, 10, 5);
+        assert_eq!(picked.clone().into_vec(), picked.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    #[should_panic(expected = "`amount` of samples must be less than or equal to `length`")]
+    fn test_sample_amount() {
+        sample(10, 11, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Sample index out of bounds")]
+    fn test_sample_index() {
+        sample(10, 3, 0).index(3);
+    }
+}
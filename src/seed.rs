@@ -1,22 +1,109 @@
-use crate::builder::RandomSequenceBuilder;
-use crate::sequence::RandomSequence;
+use crate::builder::{RandomSequenceBuilder, MIN_WIDTH, PRIMES_3_MOD_4};
+use crate::hash;
+
+/// Derive the `i`th 128-bit value from a key, with the low and high halves hashed separately.
+#[inline]
+pub(crate) const fn derive(key: &[u8], i: u64) -> u128 {
+    let low = hash::siphash(2 * i, 0, key) as u128;
+    let high = hash::siphash(2 * i + 1, 0, key) as u128;
+    high << 64 | low
+}
 
 macro_rules! seed_sequence {
-    ($type:ident) => {
-        impl RandomSequence<$type> {
-            /// Initialise a random sequence from the seeds.
+    ($type:ident, $unsigned:ident) => {
+        impl RandomSequenceBuilder<$type> {
+            /// Initialise a [RandomSequenceBuilder] from a byte key, which gives the same sequence on
+            /// every platform and crate version. The exceptions are `usize` and `isize`, whose
+            /// sequences differ between 32-bit and 64-bit platforms as the width of the type does.
             ///
-            /// These seeds should be two uniformly random numbers across the u64 space.
-            pub fn new(seed_base: $type, seed_offset: $type) -> Self {
-                let config = RandomSequenceBuilder::<$type>::new(seed_base, seed_offset);
-                config.into_iter()
+            /// Every parameter of the sequence is derived from the key rather than only the two seeds, so
+            /// that small types aren't limited to the `2^(2 * bits)` sequences of the seeds. The `i`th value
+            /// is `SipHash-2-4(k0 = 2i, k1 = 0) | SipHash-2-4(k0 = 2i + 1, k1 = 0) << 64` of the key
+            /// bytes, truncated to the width of the type, and:
+            /// - `seed_base`, `seed_offset`, `init_base`, and `init_offset` are values 0 to 3.
+            /// - `intermediate_xor` is value 4.
+            /// - `prime` is the largest `prime = 3 mod 4` of the width of the type.
+            ///
+            /// As the sequence doesn't depend on the hard coded parameters of
+            /// [RandomSequenceBuilder::new], it is stable between minor versions.
+            #[inline]
+            pub const fn from_key(key: &[u8]) -> Self {
+                Self {
+                    seed_base: derive(key, 0) as $type,
+                    seed_offset: derive(key, 1) as $type,
+                    init_base: derive(key, 2) as $unsigned,
+                    init_offset: derive(key, 3) as $unsigned,
+                    prime: PRIMES_3_MOD_4[($unsigned::BITS - MIN_WIDTH) as usize] as $unsigned,
+                    intermediate_xor: derive(key, 4) as $unsigned,
+                    len: None,
+                    min: 0,
+                }
+            }
+
+            /// Initialise a [RandomSequenceBuilder] from the UTF-8 bytes of a string key, such as
+            /// `"customer-export-2026"`. See [RandomSequenceBuilder::from_key].
+            #[inline]
+            pub const fn from_str_key(key: &str) -> Self {
+                Self::from_key(key.as_bytes())
             }
         }
     };
 }
 
-seed_sequence!(u8);
-seed_sequence!(u16);
-seed_sequence!(u32);
-seed_sequence!(u64);
-seed_sequence!(usize);
+seed_sequence!(u8, u8);
+seed_sequence!(u16, u16);
+seed_sequence!(u32, u32);
+seed_sequence!(u64, u64);
+seed_sequence!(u128, u128);
+seed_sequence!(usize, usize);
+seed_sequence!(i8, u8);
+seed_sequence!(i16, u16);
+seed_sequence!(i32, u32);
+seed_sequence!(i64, u64);
+seed_sequence!(i128, u128);
+seed_sequence!(isize, usize);
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::vec::Vec;
+
+    use super::*;
+
+    /// Evaluated at compile time, to check the keys are usable in a const context.
+    const CONFIG: RandomSequenceBuilder<u64> = RandomSequenceBuilder::<u64>::from_str_key("customer-export-2026");
+
+    #[test]
+    fn test_from_key() {
+        assert_eq!(CONFIG, RandomSequenceBuilder::<u64>::from_key(b"customer-export-2026"));
+        assert_ne!(CONFIG, RandomSequenceBuilder::<u64>::from_str_key("customer-export-2027"));
+        assert_eq!(CONFIG.prime, RandomSequenceBuilder::<u64>::new(0, 0).prime);
+
+        // the outputs must not change between versions
+        let nums: Vec<u64> = CONFIG.into_iter().take(3).collect();
+        assert_eq!(nums, [17730378442364150024, 14046225167065498529, 10024653047479453595]);
+        let nums: Vec<u8> = RandomSequenceBuilder::<u8>::from_str_key("").into_iter().take(3).collect();
+        assert_eq!(nums, [142, 174, 77]);
+        let nums: Vec<u128> =
+            RandomSequenceBuilder::<u128>::from_str_key("colours").with_bits(24).into_iter().take(3).collect();
+        assert_eq!(nums, [13687374, 15301298, 16225513]);
+    }
+
+    #[test]
+    fn test_from_key_signed() {
+        for key in ["", "a", "customer-export-2026"] {
+            let unsigned = RandomSequenceBuilder::<u32>::from_str_key(key).into_iter();
+            let signed = RandomSequenceBuilder::<i32>::from_str_key(key).into_iter();
+            assert!(unsigned.take(100).map(|num| num as i32).eq(signed.take(100)));
+        }
+    }
+
+    #[test]
+    fn test_from_key_u8() {
+        // more distinct u8 sequences than the 2^16 seed pairs allow
+        let sequences: HashSet<Vec<u8>> = (0..100_000u32)
+            .map(|key| RandomSequenceBuilder::<u8>::from_key(&key.to_le_bytes()).into_iter().collect())
+            .collect();
+        assert!(sequences.len() > 1 << 16, "{}", sequences.len());
+    }
+}
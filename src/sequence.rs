@@ -1,8 +1,14 @@
-use crate::builder::{QuadraticResidue, RandomSequenceBuilder};
+use core::num::NonZeroUsize;
+
+use num_traits::{Bounded, NumCast, One, ToPrimitive, WrappingAdd, WrappingSub, Zero};
+
+use crate::builder::{RandomSequenceBuilder, SequenceInt};
+use crate::permutation::{Permutation, Qpr};
 
 /// Generate a deterministic pseudo-random sequence of unique numbers.
 ///
-/// Not cryptographically secure.
+/// Not cryptographically secure, unless built with the keyed `Feistel` permutation from the `keyed`
+/// feature.
 ///
 /// Properties:
 /// - The sequence is deterministic and repeatable.
@@ -12,18 +18,31 @@ use crate::builder::{QuadraticResidue, RandomSequenceBuilder};
 /// Based on the article by @preshing:
 /// Article: http://preshing.com/20121224/how-to-generate-a-sequence-of-unique-random-integers/
 /// Source: https://github.com/preshing/RandomSequence/blob/master/randomsequence.h
+///
+/// The values are computed by the [Permutation] `P`, the quadratic prime residue [Qpr] by default.
+/// Other permutations are used with [RandomSequenceBuilder::into_iter_with].
 #[derive(Debug, Clone)]
-pub struct RandomSequence<T>
+pub struct RandomSequence<T, P = Qpr<T>>
 where
-    T: QuadraticResidue
+    T: SequenceInt,
+    P: Permutation<T>,
 {
     /// The config/builder holds the parameters that define the sequence.
     pub config: RandomSequenceBuilder<T>,
 
+    /// The permutation which maps each index to a value.
+    pub(crate) permutation: P,
+
     /// Internal iterator-only state.
-    pub(crate) start_index: T,
-    pub(crate) current_index: T,
-    pub(crate) intermediate_offset: T,
+    pub(crate) start_index: T::Unsigned,
+
+    /// The front and back cursors, the next indices returned by `next()` and `next_back()`.
+    pub(crate) current_index: T::Unsigned,
+    pub(crate) back_index: T::Unsigned,
+
+    /// The first and last index of the sequence, or of its shard.
+    pub(crate) first_index: T::Unsigned,
+    pub(crate) last_index: T::Unsigned,
 
     /// The end marker, required for the ExactSizeIterator so that we terminate correctly.
     pub(crate) ended: bool,
@@ -31,81 +50,264 @@ where
 
 impl<T> RandomSequence<T>
 where
-    T: QuadraticResidue
+    T: SequenceInt,
+{
+    /// Initialise a random sequence from the seeds.
+    ///
+    /// These seeds should be two uniformly random numbers across the space of `T`.
+    #[inline]
+    pub fn new(seed_base: T, seed_offset: T) -> Self {
+        T::builder(seed_base, seed_offset).into_iter()
+    }
+}
+
+impl<T, P> RandomSequence<T, P>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
 {
-    /// Get the next element in the sequence.
+    /// The permutation which maps each index to a value.
+    #[inline]
+    pub fn permutation(&self) -> &P {
+        &self.permutation
+    }
+
+    /// Get the next element in the sequence, from the front cursor.
+    #[allow(clippy::should_implement_trait)]
     #[inline]
     pub fn next(&mut self) -> Option<T> {
-        let next = self.n_internal(self.start_index.wrapping_add(&self.current_index));
-        self.current_index = match self.current_index.checked_add(&T::one()) {
-            Some(v) => {
-                self.ended = false;
-                v
-            },
-            None => {
-                if !self.ended {
-                    self.ended = true;
-                    self.current_index
-                } else {
-                    return None
-                }
-            },
-        };
+        if self.ended {
+            return None;
+        }
+        let next = self.n_unsigned(self.current_index);
+        match self.current_index == self.back_index {
+            true => self.ended = true,
+            false => self.current_index = self.current_index + T::Unsigned::one(),
+        }
         Some(next)
     }
 
-    /// Get the next element in the sequence, cycling the sequence once we reach the end.
+    /// Get the next element from the end of the sequence, from the back cursor.
     ///
-    /// This will ignore the internal [RandomSequence::ended] marker, and potentially confuse an
-    /// exact size iterator if it had reached the end.
+    /// The front and back cursors meet in the middle, so that each element is returned once by
+    /// either [RandomSequence::next] or [RandomSequence::next_back].
     #[inline]
-    pub fn wrapping_next(&mut self) -> T {
-        let next = self.n_internal(self.start_index.wrapping_add(&self.current_index));
-        self.current_index = self.current_index.wrapping_add(&T::one());
-        next
+    pub fn next_back(&mut self) -> Option<T> {
+        if self.ended {
+            return None;
+        }
+        let next = self.n_unsigned(self.back_index);
+        match self.back_index == self.current_index {
+            true => self.ended = true,
+            false => self.back_index = self.back_index - T::Unsigned::one(),
+        }
+        Some(next)
     }
 
-    /// Get the previous element in the sequence.
+    /// Skip the next `n` elements in O(1), as if calling [RandomSequence::next] `n` times.
+    ///
+    /// Returns the number of steps that couldn't be taken if the sequence was exhausted first, as
+    /// with the unstable `Iterator::advance_by`.
     #[inline]
-    pub fn prev(&mut self) -> Option<T> {
-        // decrement then compute, opposite to next()
-        self.current_index = match self.current_index.checked_sub(&T::one()) {
-            Some(v) => v,
-            None => return None,
-        };
-        self.ended = false;
-        Some(self.n_internal(self.start_index.wrapping_add(&self.current_index)))
+    pub fn advance_by(&mut self, n: usize) -> Result<(), NonZeroUsize> {
+        if n == 0 {
+            return Ok(());
+        }
+        if self.ended {
+            return Err(NonZeroUsize::new(n).unwrap());
+        }
+
+        // the nth call to next() returns the element at current_index + n - 1
+        let step = n - 1;
+        match (self.back_index - self.current_index).to_usize() {
+            Some(distance) if step >= distance => {
+                self.current_index = self.back_index;
+                self.ended = true;
+                NonZeroUsize::new(step - distance).map_or(Ok(()), Err)
+            },
+            _ => {
+                self.current_index = self.current_index + <T::Unsigned as NumCast>::from(n).unwrap();
+                Ok(())
+            },
+        }
     }
 
-    /// Get the previous element in the sequence, cycling the sequence once we reach the start.
+    /// Skip the next `n` elements from the end in O(1), as if calling [RandomSequence::next_back]
+    /// `n` times.
+    ///
+    /// Returns the number of steps that couldn't be taken if the sequence was exhausted first, as
+    /// with the unstable `DoubleEndedIterator::advance_back_by`.
     #[inline]
-    pub fn wrapping_prev(&mut self) -> T {
-        // decrement then compute, opposite to next()
-        self.current_index = self.current_index.wrapping_sub(&T::one());
-        self.n_internal(self.start_index.wrapping_add(&self.current_index))
+    pub fn advance_back_by(&mut self, n: usize) -> Result<(), NonZeroUsize> {
+        if n == 0 {
+            return Ok(());
+        }
+        if self.ended {
+            return Err(NonZeroUsize::new(n).unwrap());
+        }
+
+        // the nth call to next_back() returns the element at back_index - n + 1
+        let step = n - 1;
+        match (self.back_index - self.current_index).to_usize() {
+            Some(distance) if step >= distance => {
+                self.back_index = self.current_index;
+                self.ended = true;
+                NonZeroUsize::new(step - distance).map_or(Ok(()), Err)
+            },
+            _ => {
+                self.back_index = self.back_index - <T::Unsigned as NumCast>::from(n).unwrap();
+                Ok(())
+            },
+        }
     }
 
     /// Get the nth element in the sequence.
+    ///
+    /// For a sequence bounded with [RandomSequenceBuilder::with_len], the index wraps around the
+    /// length of the sequence. Negative indices count backwards from the end of the sequence.
     #[inline]
     pub fn n(&self, index: T) -> T {
-        let actual_index = self.start_index.wrapping_add(&index);
-        self.n_internal(actual_index)
+        self.n_unsigned(self.unsigned_index(index))
+    }
+
+    /// Convert an index to within `[0, len)`, wrapping around the length of the sequence.
+    #[inline(always)]
+    pub(crate) fn unsigned_index(&self, index: T) -> T::Unsigned {
+        match self.config.len {
+            None => index.to_unsigned(),
+            Some(len) => index.wrapping_index(len),
+        }
+    }
+
+    /// Get the nth element in the sequence from an index within `[0, len)`.
+    #[inline(always)]
+    pub(crate) fn n_unsigned(&self, index: T::Unsigned) -> T {
+        self.output(self.n_internal(self.actual_index(index)))
+    }
+
+    /// Convert an index within `[0, len)` to the absolute index, `(start_index + index) % len`.
+    #[inline(always)]
+    pub(crate) fn actual_index(&self, index: T::Unsigned) -> T::Unsigned {
+        match self.config.len {
+            None => self.start_index.wrapping_add(&index),
+            Some(len) => {
+                // (start_index + index) % len; but done safely to avoid integer overflow
+                let remaining = len - self.start_index;
+                match index >= remaining {
+                    true => index - remaining,
+                    false => self.start_index + index,
+                }
+            },
+        }
+    }
+
+    /// Offset a permuted value within `[0, len)` by the start of the range.
+    #[inline(always)]
+    pub(crate) fn output(&self, value: T::Unsigned) -> T {
+        T::from_unsigned(self.config.min.to_unsigned().wrapping_add(&value))
     }
 
     /// Get the nth element in the sequence, but using the absolute index rather than relative to `start_index`.
     ///
-    /// `qpr(qpr(index + intermediate_offset) ^ intermediate_xor)`
+    /// Bounded sequences cycle-walk the permutation until the output falls within `[0, len)`, which
+    /// is guaranteed to terminate as the walk must eventually return to `index`.
+    #[inline(always)]
+    fn n_internal(&self, index: T::Unsigned) -> T::Unsigned {
+        let mut value = self.permute(index);
+        if let Some(len) = self.config.len {
+            while value >= len {
+                value = self.permute(value);
+            }
+        }
+        value
+    }
+
+    /// The permutation over the power of two domain `[0, mask]`.
     #[inline(always)]
-    fn n_internal(&self, index: T) -> T {
-        let inner_residue = self.config.permute_qpr(index).wrapping_add(&self.intermediate_offset);
-        self.config.permute_qpr(inner_residue ^ self.config.intermediate_xor)
+    pub(crate) fn permute(&self, index: T::Unsigned) -> T::Unsigned {
+        self.permutation.apply(index)
+    }
+
+    /// Get the index of a value in the sequence, the inverse of [RandomSequence::n], such that
+    /// `sequence.index_of(sequence.n(index)) == index`.
+    ///
+    /// This is an O(1) operation, although slower than [RandomSequence::n] as the default permutation
+    /// computes modular square roots.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is outside of a sequence bounded with [RandomSequenceBuilder::with_len]
+    /// or [RandomSequenceBuilder::with_range], or if the permutation can't be inverted.
+    #[inline]
+    pub fn index_of(&self, value: T) -> T {
+        let index = self.index_of_unsigned(value).expect("RandomSequence value out of bounds");
+        T::from_index(index, self.config.len)
+    }
+
+    /// Check if the value is one of the first `k` elements of the sequence, `n(0)..n(k)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the permutation can't be inverted.
+    #[inline]
+    pub fn contains_before(&self, value: T, k: T) -> bool {
+        match self.index_of_unsigned(value) {
+            Some(index) => k > T::zero() && index < k.to_unsigned(),
+            None => false,
+        }
+    }
+
+    /// Get the index of a value within `[0, len)`, or `None` if the value is out of bounds.
+    #[inline]
+    fn index_of_unsigned(&self, value: T) -> Option<T::Unsigned> {
+        let value = value.to_unsigned().wrapping_sub(&self.config.min.to_unsigned());
+        if value > self.max_index() {
+            return None;
+        }
+
+        let actual_index = self.index_internal(value);
+        Some(match self.config.len {
+            None => actual_index.wrapping_sub(&self.start_index),
+            Some(len) => match actual_index >= self.start_index {
+                true => actual_index - self.start_index,
+                false => len - self.start_index + actual_index,
+            },
+        })
+    }
+
+    /// Get the absolute index of a value, the inverse of [RandomSequence::n_internal].
+    #[inline]
+    fn index_internal(&self, value: T::Unsigned) -> T::Unsigned {
+        let mut index = self.invert(value);
+        if let Some(len) = self.config.len {
+            while index >= len {
+                index = self.invert(index);
+            }
+        }
+        index
+    }
+
+    /// The inverse of [RandomSequence::permute].
+    #[inline]
+    fn invert(&self, value: T::Unsigned) -> T::Unsigned {
+        self.permutation.invert(value).expect("RandomSequence permutation can't be inverted")
     }
 
-    /// Get the current position in the sequence. Will return `None` if the sequence has been exhausted.
+    /// The final index in the whole sequence, ignoring shards.
+    #[inline]
+    pub(crate) fn max_index(&self) -> T::Unsigned {
+        match self.config.len {
+            Some(len) => len - T::Unsigned::one(),
+            None => T::Unsigned::max_value(),
+        }
+    }
+
+    /// Get the current position of the front cursor in the sequence. Will return `None` if the
+    /// sequence has been exhausted.
     #[inline]
     pub fn index(&self) -> Option<T> {
         match self.ended {
-            false => Some(self.current_index),
+            false => Some(T::from_index(self.current_index, self.config.len)),
             true => None,
         }
     }
@@ -116,51 +318,172 @@ where
         self.ended
     }
 
-    /// Set the index for the iterator.
+    /// Set the index of the front cursor. Negative indices count backwards from the end of the
+    /// sequence.
+    ///
+    /// The back cursor is reset to the end of the sequence, or of its shard, so that every element
+    /// from the index onwards remains. If the iterator was exhausted, this will reset it to the index
+    /// set.
     ///
-    /// If the iterator was exhausted, this will reset it to the index set.
+    /// # Panics
+    ///
+    /// Panics if the index is outside of a sequence bounded with [RandomSequenceBuilder::with_len]
+    /// or [RandomSequenceBuilder::with_range], or outside of a shard, including any index of an
+    /// empty shard.
     #[inline]
     pub fn set_index(&mut self, index: T) {
+        let index = match self.config.len {
+            None => index.to_unsigned(),
+            Some(len) => index.checked_index(len).expect("RandomSequence index out of bounds"),
+        };
+        assert!(index >= self.first_index && index <= self.last_index, "RandomSequence index out of bounds");
         self.current_index = index;
+        self.back_index = self.last_index;
         self.ended = false;
     }
-}
 
-macro_rules! impl_unsized_iterator {
-    ($T:ident) => {
-        impl Iterator for RandomSequence<$T> {
-            type Item = $T;
+    /// Split the sequence into `K` disjoint shards, where shard `i` is [RandomSequence::shard]`(i, K)`.
+    #[inline]
+    pub fn split<const K: usize>(&self) -> [Self; K] {
+        core::array::from_fn(|i| self.shard(i, K))
+    }
 
-            #[inline]
-            fn next(&mut self) -> Option<Self::Item> {
-                self.next()
-            }
+    /// Get shard `i` of `k`, which iterates over a contiguous block of roughly `len / k` indices of
+    /// this sequence.
+    ///
+    /// The `k` shards are disjoint and together cover every index of this sequence in order, so that
+    /// `k` workers can each take a unique subset of the sequence without coordination. Each shard has
+    /// its own length and stops at its own boundary, while [RandomSequence::n],
+    /// [RandomSequence::index], and [RandomSequence::set_index] use the indices of the whole
+    /// sequence. Shards can be sharded further.
+    ///
+    /// The front and back cursors are ignored, and each shard starts from its first index. If there are fewer
+    /// indices than shards, the trailing shards are empty and already exhausted, and have no index to
+    /// move to with [RandomSequence::set_index] or to cycle around with a [Cursor](crate::Cursor).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i >= k`.
+    #[inline]
+    pub fn shard(&self, i: usize, k: usize) -> Self {
+        assert!(i < k, "RandomSequence shard out of bounds");
+        if self.is_empty_shard() {
+            return self.clone();
+        }
 
-            #[inline]
-            fn size_hint(&self) -> (usize, Option<usize>) {
-                ($T::MAX as usize - self.current_index as usize, None)
-            }
+        // len = base * k + extra, with extra in [1, k], such that the first `extra` shards take one more
+        // index; computed from `len - 1` as the length overflows for a sequence covering the entire type
+        let last = (self.last_index - self.first_index).to_u128().unwrap();
+        let (i, k) = (i as u128, k as u128);
+        let (base, extra) = (last / k, last % k + 1);
+        let start = |i: u128| i * base + i.min(extra);
+        let first = start(i);
+        let end = match i + 1 == k {
+            true => last,
+            false => start(i + 1) - 1,
+        };
+
+        let mut shard = self.clone();
+        match first <= end {
+            true => {
+                shard.first_index = self.first_index + <T::Unsigned as NumCast>::from(first).unwrap();
+                shard.last_index = self.first_index + <T::Unsigned as NumCast>::from(end).unwrap();
+                shard.ended = false;
+            },
+            false => {
+                // an empty window, which no index falls within
+                shard.first_index = T::Unsigned::one();
+                shard.last_index = T::Unsigned::zero();
+                shard.current_index = T::Unsigned::zero();
+                shard.back_index = T::Unsigned::zero();
+                shard.ended = true;
+                return shard;
+            },
         }
-    };
+        shard.current_index = shard.first_index;
+        shard.back_index = shard.last_index;
+        shard
+    }
+
+    /// Check if this is an empty shard, from sharding a sequence into more shards than it has
+    /// indices.
+    #[inline]
+    pub(crate) fn is_empty_shard(&self) -> bool {
+        self.first_index > self.last_index
+    }
+
+    /// The number of elements remaining in the sequence, between the front and back cursors, or
+    /// `None` if it overflows `usize`.
+    ///
+    /// Unlike [ExactSizeIterator::len], which is only implemented for types whose full domain fits
+    /// in `usize`, this is available for every type, such as a bounded `u64` sequence.
+    ///
+    /// ```
+    /// use rand_unique::{RandomSequence, RandomSequenceBuilder};
+    ///
+    /// let mut sequence = RandomSequenceBuilder::<u64>::new(1, 2).with_len(100).into_iter();
+    /// assert_eq!(sequence.remaining(), Some(100));
+    /// sequence.next();
+    /// sequence.next_back();
+    /// assert_eq!(sequence.remaining(), Some(98));
+    ///
+    /// // the full domain of u128 doesn't fit in usize
+    /// assert_eq!(RandomSequence::<u128>::new(1, 2).remaining(), None);
+    /// ```
+    #[inline]
+    pub fn remaining(&self) -> Option<usize> {
+        if self.ended {
+            return Some(0);
+        }
+        (self.back_index - self.current_index).to_usize()?.checked_add(1)
+    }
 }
 
-macro_rules! impl_exact_size_iterator {
-    ($T:ident) => {
-        impl Iterator for RandomSequence<$T> {
-            type Item = $T;
+impl<T, P> Iterator for RandomSequence<T, P>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+    type Item = T;
 
-            #[inline]
-            fn next(&mut self) -> Option<Self::Item> {
-                self.next()
-            }
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next()
+    }
 
-            #[inline]
-            fn size_hint(&self) -> (usize, Option<usize>) {
-                ($T::MAX as usize + 1 - self.current_index as usize, Some($T::MAX as usize + 1 - self.current_index as usize))
-            }
+    /// The size is exact unless the remaining elements overflow `usize`, which is only possible for
+    /// most of the domain of a 64-bit or 128-bit type.
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.remaining() {
+            Some(remaining) => (remaining, Some(remaining)),
+            None => (usize::MAX, None),
+        }
+    }
+
+    #[inline]
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.advance_by(n).ok()?;
+        self.next()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.remaining().expect("RandomSequence count overflows usize")
+    }
+
+    #[inline]
+    fn last(self) -> Option<Self::Item> {
+        match self.ended {
+            false => Some(self.n_unsigned(self.back_index)),
+            true => None,
         }
+    }
+}
 
-        impl ExactSizeIterator for RandomSequence<$T> {}
+macro_rules! impl_exact_size_iterator {
+    ($T:ident) => {
+        impl<P> ExactSizeIterator for RandomSequence<$T, P> where P: Permutation<$T> {}
     };
 }
 
@@ -169,26 +492,31 @@ impl_exact_size_iterator!(u8);
 impl_exact_size_iterator!(u16);
 #[cfg(target_pointer_width = "64")]
 impl_exact_size_iterator!(u32);
-#[cfg(target_pointer_width = "32")]
-impl_unsized_iterator!(u32);
-impl_unsized_iterator!(u64);
-impl_unsized_iterator!(usize);
+impl_exact_size_iterator!(i8);
+impl_exact_size_iterator!(i16);
+#[cfg(target_pointer_width = "64")]
+impl_exact_size_iterator!(i32);
 
-impl<T> DoubleEndedIterator for RandomSequence<T>
+impl<T, P> DoubleEndedIterator for RandomSequence<T, P>
 where
-    T: QuadraticResidue,
-    RandomSequence<T>: Iterator<Item = T>,
+    T: SequenceInt,
+    P: Permutation<T>,
 {
     #[inline]
     fn next_back(&mut self) -> Option<Self::Item> {
-        self.prev()
+        self.next_back()
+    }
+
+    #[inline]
+    fn nth_back(&mut self, n: usize) -> Option<Self::Item> {
+        self.advance_back_by(n).ok()?;
+        self.next_back()
     }
 }
 
 impl<T> From<RandomSequenceBuilder<T>> for RandomSequence<T>
 where
-    T: QuadraticResidue,
-    RandomSequence<T>: Iterator<Item = T>,
+    T: SequenceInt,
 {
     #[inline]
     fn from(value: RandomSequenceBuilder<T>) -> Self {
@@ -279,6 +607,7 @@ mod tests {
     test_sequence!(test_u16_sequence, u16, 65536);
     test_sequence!(test_u32_sequence, u32, 100_000);
     test_sequence!(test_u64_sequence, u64, 100_000);
+    test_sequence!(test_u128_sequence, u128, 100_000);
     test_sequence!(test_usize_sequence, usize, 100_000);
 
     macro_rules! test_exact_size_iterator {
@@ -294,13 +623,13 @@ mod tests {
                 assert_eq!(sequence.len(), $type::MAX as usize - 1);
                 sequence.next();
                 assert_eq!(sequence.len(), $type::MAX as usize - 2);
-                sequence.prev();
-                assert_eq!(sequence.len(), $type::MAX as usize - 1);
+                sequence.next_back();
+                assert_eq!(sequence.len(), $type::MAX as usize - 3);
 
                 sequence.next();
                 sequence.next();
                 sequence.next();
-                assert_eq!(sequence.len(), $type::MAX as usize - 4);
+                assert_eq!(sequence.len(), $type::MAX as usize - 6);
 
                 // don't collect a u32, only test this on u8 and u16
                 if sequence.len() <= u16::MAX as usize + 1 {
@@ -313,7 +642,7 @@ mod tests {
                     assert_eq!(sequence.len(), $type::MAX as usize + 1);
                     let all_items: Vec<_> = sequence.collect();
                     assert_eq!(all_items.len(), $type::MAX as usize + 1);
-                    assert_eq!(all_items.len(), remaining_items.len() + 5);
+                    assert_eq!(all_items.len(), remaining_items.len() + 7);
                 }
             }
         };
@@ -324,6 +653,528 @@ mod tests {
     #[cfg(target_pointer_width = "64")]
     test_exact_size_iterator!(test_u32_exact_size_iterator, u32);
 
+    macro_rules! test_bounded_sequence {
+        ($name:ident, $type:ident) => {
+            #[test]
+            fn $name() {
+                for len in [1, 2, 3, 5, 100, 127, 128, 129, 200, 255] {
+                    let config = RandomSequenceBuilder::<$type>::new(3, 7).with_len(len);
+                    let sequence = config.into_iter();
+
+                    // check the sequence is a permutation of [0, len)
+                    let nums: Vec<$type> = sequence.clone().collect();
+                    assert_eq!(nums.len(), len as usize);
+                    let unique: HashSet<$type> = nums.iter().copied().collect();
+                    assert_eq!(unique.len(), len as usize);
+                    assert!(nums.iter().all(|&num| num < len));
+
+                    // check n() agrees with the iterator, and wraps around the len
+                    for (i, &num) in nums.iter().enumerate() {
+                        assert_eq!(sequence.n(i as $type), num);
+                        if let Some(wrapped) = (i as $type).checked_add(len) {
+                            assert_eq!(sequence.n(wrapped), num);
+                        }
+                    }
+
+                    // check rev() walks back from the end, and next() and next_back() meet in the middle
+                    let reversed: Vec<$type> = config.into_iter().rev().collect();
+                    assert!(reversed.iter().eq(nums.iter().rev()));
+                    let mut sequence = config.into_iter();
+                    let mut front = Vec::new();
+                    let mut back = Vec::new();
+                    while let Some(num) = sequence.next() {
+                        front.push(num);
+                        back.extend(sequence.next_back());
+                        assert_eq!(sequence.size_hint().0, nums.len() - front.len() - back.len());
+                    }
+                    assert!(sequence.exhausted());
+                    assert_eq!(sequence.next_back(), None);
+                    assert!(front.iter().chain(back.iter().rev()).eq(nums.iter()));
+
+                    // check exhaustion and the size hint
+                    let mut sequence = config.into_iter();
+                    assert_eq!(sequence.size_hint(), (len as usize, Some(len as usize)));
+                    assert_eq!(sequence.remaining(), Some(len as usize));
+                    sequence.set_index(len - 1);
+                    assert_eq!(sequence.size_hint(), (1, Some(1)));
+                    assert_eq!(sequence.remaining(), Some(1));
+                    assert_eq!(sequence.next(), nums.last().copied());
+                    assert!(sequence.exhausted());
+                    assert_eq!(sequence.size_hint(), (0, Some(0)));
+                    assert_eq!(sequence.remaining(), Some(0));
+                    assert!(sequence.next().is_none());
+
+                    // check wrapping
+                    sequence.set_index(len - 1);
+                    let mut cursor = sequence.into_cursor();
+                    assert_eq!(cursor.wrapping_next(), nums[len as usize - 1]);
+                    assert_eq!(cursor.index(), Some(0));
+                    assert_eq!(cursor.wrapping_prev(), nums[len as usize - 1]);
+                    assert_eq!(cursor.index(), Some(len - 1));
+                }
+            }
+        };
+    }
+
+    test_bounded_sequence!(test_u8_bounded_sequence, u8);
+    test_bounded_sequence!(test_u16_bounded_sequence, u16);
+    test_bounded_sequence!(test_u32_bounded_sequence, u32);
+    test_bounded_sequence!(test_u64_bounded_sequence, u64);
+    test_bounded_sequence!(test_u128_bounded_sequence, u128);
+    test_bounded_sequence!(test_usize_bounded_sequence, usize);
+
+    #[test]
+    fn test_bounded_exact_size_iterator() {
+        let mut sequence = RandomSequenceBuilder::<u32>::new(0, 0).with_len(1_000_000).into_iter();
+        assert_eq!(sequence.len(), 1_000_000);
+        sequence.next();
+        assert_eq!(sequence.len(), 999_999);
+
+        let nums: HashSet<u32> = RandomSequenceBuilder::<u32>::new(1, 2).with_len(1_000_000).into_iter().collect();
+        assert_eq!(nums.len(), 1_000_000);
+        assert!(nums.iter().all(|&num| num < 1_000_000));
+        // wide types have no len(), but report the remaining elements of a bounded sequence
+        let mut sequence = RandomSequenceBuilder::<i64>::new(0, 0).with_range(-500..500).into_iter();
+        assert_eq!(sequence.remaining(), Some(1000));
+        sequence.next_back();
+        assert_eq!(sequence.remaining(), Some(999));
+        assert_eq!(RandomSequence::<u64>::new(0, 0).remaining(), None);
+    }
+
+    macro_rules! test_with_bits {
+        ($name:ident, $type:ident) => {
+            #[test]
+            fn $name() {
+                let width = $type::BITS;
+                for bits in [1, 2, 3, 8, 24, 48, 53, 63, 64, 127, 128].into_iter().filter(|&bits| bits <= width) {
+                    let config = RandomSequenceBuilder::<$type>::new(3, 7).with_bits(bits);
+                    let sequence = config.into_iter();
+
+                    // the domain is exactly 2^bits, or the entire type
+                    let max = ($type::MAX >> (width - bits)) as u128;
+                    match bits < width {
+                        true => assert_eq!(config.len.map(|len| len as u128), Some(max + 1)),
+                        false => assert_eq!(config.len, None),
+                    }
+                    assert_eq!(
+                        config.prime as u128,
+                        crate::primes::largest_3_mod_4_prime_below(max.max(3).saturating_add(1)).unwrap()
+                    );
+                    assert_eq!(sequence.size_hint().0 as u128, max.saturating_add(1).min(usize::MAX as u128));
+
+                    // outputs stay below 2^bits, and are unique
+                    let nums: Vec<$type> = sequence.clone().take(1000).collect();
+                    assert!(nums.iter().all(|&num| num as u128 <= max));
+                    let unique: HashSet<$type> = nums.iter().copied().collect();
+                    assert_eq!(unique.len(), nums.len());
+
+                    // indexing from the end, prev, and index_of
+                    let mut sequence = config.into_iter();
+                    sequence.set_index(max as $type);
+                    let last = sequence.n(max as $type);
+                    assert!(last as u128 <= max);
+                    assert_eq!(sequence.next(), Some(last));
+                    assert!(sequence.exhausted());
+                    assert_eq!(sequence.clone().into_cursor().prev(), (max > 0).then(|| sequence.n(max as $type - 1)));
+                    assert_eq!(sequence.index_of(last), max as $type);
+                }
+            }
+        };
+    }
+
+    test_with_bits!(test_u8_with_bits, u8);
+    test_with_bits!(test_u16_with_bits, u16);
+    test_with_bits!(test_u32_with_bits, u32);
+    test_with_bits!(test_u64_with_bits, u64);
+    test_with_bits!(test_u128_with_bits, u128);
+
+    #[test]
+    fn test_with_bits_exact() {
+        let sequence = RandomSequenceBuilder::<u32>::new(0, 0).with_bits(24).into_iter();
+        assert_eq!(sequence.len(), 1 << 24);
+
+        let nums: HashSet<u16> = RandomSequenceBuilder::<u16>::new(1, 2).with_bits(12).into_iter().collect();
+        assert_eq!(nums, (0..1 << 12).collect());
+
+        // signed types are non-negative unless the entire type is covered
+        let nums: HashSet<i8> = RandomSequenceBuilder::<i8>::new(1, 2).with_bits(7).into_iter().collect();
+        assert_eq!(nums, (0..=i8::MAX).collect());
+        let nums: HashSet<i8> = RandomSequenceBuilder::<i8>::new(1, 2).with_bits(8).into_iter().collect();
+        assert_eq!(nums.len(), 256);
+    }
+
+    #[test]
+    #[should_panic(expected = "RandomSequenceBuilder bits out of range")]
+    fn test_with_bits_out_of_range() {
+        RandomSequenceBuilder::<u32>::new(0, 0).with_bits(33);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_bounded_set_index_out_of_bounds() {
+        let mut sequence = RandomSequenceBuilder::<u16>::new(0, 0).with_len(100).into_iter();
+        sequence.set_index(100);
+    }
+
+    macro_rules! test_index_of {
+        ($name:ident, $type:ident) => {
+            #[test]
+            fn $name() {
+                let sequence = RandomSequenceBuilder::<$type>::new(5, 9).into_iter();
+                for i in (0..1000).chain(($type::MAX as u64).saturating_sub(1000)..=$type::MAX as u64) {
+                    let index = i as $type;
+                    let value = sequence.n(index);
+                    assert_eq!(sequence.index_of(value), index);
+                    assert!(sequence.contains_before(value, index.wrapping_add(1)) || index == $type::MAX);
+                    assert!(!sequence.contains_before(value, index));
+                }
+
+                for len in [1, 2, 3, 100, 255] {
+                    let sequence = RandomSequenceBuilder::<$type>::new(5, 9).with_len(len).into_iter();
+                    for index in 0..len {
+                        assert_eq!(sequence.index_of(sequence.n(index)), index);
+                    }
+                    assert!(!sequence.contains_before(len, len));
+                }
+            }
+        };
+    }
+
+    test_index_of!(test_u8_index_of, u8);
+    test_index_of!(test_u16_index_of, u16);
+    test_index_of!(test_u32_index_of, u32);
+    test_index_of!(test_u64_index_of, u64);
+    test_index_of!(test_u128_index_of, u128);
+    test_index_of!(test_usize_index_of, usize);
+
+    macro_rules! test_signed_sequence {
+        ($name:ident, $type:ident, $unsigned:ident, $min:literal, $max:literal) => {
+            #[test]
+            fn $name() {
+                // the full range matches the unsigned sequence cast to the signed type
+                let sequence = RandomSequenceBuilder::<$type>::new(-3, 7).into_iter();
+                let unsigned = RandomSequenceBuilder::<$unsigned>::new(-3 as $type as $unsigned, 7).into_iter();
+                for (i, num) in std::iter::zip(0..1000, sequence.clone()) {
+                    assert_eq!(num, unsigned.n(i as $unsigned) as $type);
+                    assert_eq!(sequence.n(i as $type), num);
+                    assert_eq!(sequence.index_of(num), i as $type);
+                }
+
+                // negative indices count back from the end
+                assert_eq!(sequence.n(-1), unsigned.n($unsigned::MAX) as $type);
+                let mut sequence = sequence.clone();
+                sequence.set_index(-1);
+                assert_eq!(sequence.next(), Some(unsigned.n($unsigned::MAX) as $type));
+                assert!(sequence.exhausted());
+                assert!(sequence.next().is_none());
+
+                // check a signed sub-range is a permutation of the range
+                let config = RandomSequenceBuilder::<$type>::new(-3, 7).with_range($min..$max);
+                let len = ($max - $min) as usize;
+                assert_eq!(config.min, $min);
+                assert_eq!(config.len, Some(len as $unsigned));
+
+                let sequence = config.into_iter();
+                let nums: Vec<$type> = sequence.clone().collect();
+                assert_eq!(nums.len(), len);
+                assert_eq!(nums.iter().collect::<HashSet<_>>().len(), len);
+                assert!(nums.iter().all(|num| ($min..$max).contains(num)));
+
+                for (i, &num) in nums.iter().enumerate() {
+                    let index = sequence.index_of(num);
+                    assert_eq!(sequence.n(index), num);
+                    if let Ok(i) = $type::try_from(i) {
+                        assert_eq!(index, i);
+                    } else {
+                        assert_eq!(index as isize, i as isize - len as isize);
+                    }
+                }
+                assert_eq!(sequence.n(-1), nums[len - 1]);
+                assert_eq!(sequence.n(-2), nums[len - 2]);
+                assert!(!sequence.contains_before($max, $type::MAX));
+                assert!(!sequence.contains_before(nums[0], -1));
+                assert!(sequence.contains_before(nums[0], 1));
+
+                let mut sequence = config.into_iter();
+                sequence.set_index(-2);
+                assert_eq!(sequence.index(), $type::try_from(len - 2).ok().or(Some(-2)));
+                assert_eq!(sequence.size_hint(), (2, Some(2)));
+            }
+        };
+    }
+
+    test_signed_sequence!(test_i8_sequence, i8, u8, -100, 100);
+    test_signed_sequence!(test_i16_sequence, i16, u16, -500, 500);
+    test_signed_sequence!(test_i32_sequence, i32, u32, -500, 500);
+    test_signed_sequence!(test_i64_sequence, i64, u64, -500, 500);
+    test_signed_sequence!(test_i128_sequence, i128, u128, -500, 500);
+    test_signed_sequence!(test_isize_sequence, isize, usize, -500, 500);
+
+    #[test]
+    fn test_full_range() {
+        let nums: HashSet<i8> = RandomSequenceBuilder::<i8>::new(0, 0).with_range(..).into_iter().collect();
+        assert_eq!(nums.len(), 256);
+
+        let config = RandomSequenceBuilder::<u8>::new(0, 0).with_range(0..=u8::MAX);
+        assert_eq!(config, RandomSequenceBuilder::<u8>::new(0, 0));
+
+        let config = RandomSequenceBuilder::<u16>::new(0, 0).with_range(1000..=2000);
+        let nums: HashSet<u16> = config.into_iter().collect();
+        assert_eq!(nums, (1000..=2000).collect());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_signed_set_index_out_of_bounds() {
+        let mut sequence = RandomSequenceBuilder::<i16>::new(0, 0).with_range(-50..50).into_iter();
+        sequence.set_index(-101);
+    }
+
+    macro_rules! test_shard {
+        ($name:ident, $type:ident) => {
+            #[test]
+            fn $name() {
+                for config in [
+                    RandomSequenceBuilder::<$type>::new(0x12, 0x56).with_len(1),
+                    RandomSequenceBuilder::<$type>::new(0x12, 0x56).with_len(3),
+                    RandomSequenceBuilder::<$type>::new(0x12, 0x56).with_len(100),
+                    RandomSequenceBuilder::<$type>::new(0x12, 0x56).with_range(..),
+                ] {
+                    let sequence = config.into_iter();
+                    let expected: Vec<$type> = sequence.clone().take(1000).collect();
+
+                    for k in [1, 2, 3, 7, 100] {
+                        // the shards cover the sequence in order
+                        let mut nums = Vec::new();
+                        for i in 0..k {
+                            let shard = sequence.shard(i, k);
+                            nums.extend(shard.take(1000 - nums.len()));
+                        }
+                        assert_eq!(nums, expected);
+                    }
+
+                    // shards stop at their own boundaries
+                    let [first, second] = sequence.split::<2>();
+                    let len = first.size_hint().0;
+                    if config.len.is_some() {
+                        assert_eq!(first.size_hint().0 + second.size_hint().0, expected.len());
+                        assert_eq!(first.clone().last(), Some(expected[len - 1]));
+                    }
+                    let last = sequence.n($type::from_index(first.last_index, config.len));
+                    assert_eq!(first.clone().next_back(), Some(last));
+                    let mut shard = first.clone().into_cursor();
+                    assert_eq!(shard.prev(), None);
+                    assert_eq!(shard.wrapping_prev(), last);
+                    assert_eq!(shard.wrapping_next(), last);
+                    assert_eq!(shard.index(), Some(0));
+                    assert_eq!(shard.wrapping_next(), expected[0]);
+
+                    // shards use the indices of the whole sequence, and can be sharded further
+                    let mut shard = second.shard(1, 2);
+                    if let Some(index) = shard.index() {
+                        assert_eq!(shard.next(), Some(sequence.n(index)));
+                        assert_eq!(sequence.index_of(sequence.n(index)), index);
+                    }
+                }
+            }
+        };
+    }
+
+    test_shard!(test_u8_shard, u8);
+    test_shard!(test_u16_shard, u16);
+    test_shard!(test_u32_shard, u32);
+    test_shard!(test_u64_shard, u64);
+    test_shard!(test_u128_shard, u128);
+    test_shard!(test_usize_shard, usize);
+    test_shard!(test_i8_shard, i8);
+    test_shard!(test_i64_shard, i64);
+
+    macro_rules! test_nth {
+        ($name:ident, $type:ident) => {
+            #[test]
+            fn $name() {
+                let config = RandomSequenceBuilder::<$type>::new(0x12, 0x56);
+                for sequence in [
+                    config.into_iter(),
+                    config.with_len(1).into_iter(),
+                    config.with_len(100).into_iter(),
+                    config.with_len(100).into_iter().shard(1, 3),
+                    config.with_len(2).into_iter().shard(2, 3),
+                ] {
+                    let last = match sequence.config.len {
+                        Some(_) => sequence.clone().fold(None, |_, num| Some(num)),
+                        None => Some(sequence.n($type::from_unsigned(sequence.last_index))),
+                    };
+
+                    for start in [0, 1, 50, 98, 99, 200] {
+                        let mut sequence = sequence.clone();
+                        sequence.advance_by(start).ok();
+
+                        for n in [0, 1, 2, 10, 48, 49, 50, 99, 100, 1000] {
+                            // matches calling next() and prev() n + 1 times
+                            let mut expected = sequence.clone();
+                            let num = (0..=n).map(|_| expected.next()).last().unwrap();
+                            let mut actual = sequence.clone();
+                            assert_eq!(actual.nth(n), num);
+                            assert_eq!((actual.current_index, actual.ended), (expected.current_index, expected.ended));
+
+                            let mut expected = sequence.clone();
+                            let num = (0..=n).map(|_| expected.next_back()).last().unwrap();
+                            let mut actual = sequence.clone();
+                            assert_eq!(actual.nth_back(n), num);
+                            assert_eq!((actual.back_index, actual.ended), (expected.back_index, expected.ended));
+
+                            let mut expected = sequence.clone();
+                            let taken = (0..n).take_while(|_| expected.next().is_some()).count();
+                            let mut actual = sequence.clone();
+                            assert_eq!(
+                                actual.advance_by(n).map_err(|e| e.get()),
+                                match n - taken {
+                                    0 => Ok(()),
+                                    shortfall => Err(shortfall),
+                                }
+                            );
+                            assert_eq!((actual.current_index, actual.ended), (expected.current_index, expected.ended));
+                        }
+
+                        if sequence.config.len.is_some() {
+                            assert_eq!(sequence.clone().count(), sequence.clone().fold(0, |count, _| count + 1));
+                            assert_eq!(sequence.clone().step_by(7).collect::<Vec<_>>(), {
+                                let nums: Vec<$type> = sequence.clone().collect();
+                                nums.into_iter().step_by(7).collect::<Vec<_>>()
+                            });
+                        }
+                        assert_eq!(sequence.clone().last(), last.filter(|_| !sequence.exhausted()));
+                    }
+                }
+            }
+        };
+    }
+
+    test_nth!(test_u8_nth, u8);
+    test_nth!(test_u16_nth, u16);
+    test_nth!(test_u32_nth, u32);
+    test_nth!(test_u64_nth, u64);
+    test_nth!(test_u128_nth, u128);
+    test_nth!(test_usize_nth, usize);
+    test_nth!(test_i8_nth, i8);
+    test_nth!(test_i64_nth, i64);
+
+    #[test]
+    fn test_nth_large() {
+        let sequence = RandomSequence::<u64>::new(3, 4);
+        let n = 1_000_000_000_000;
+        assert_eq!(sequence.clone().nth(n), Some(sequence.n(n as u64)));
+        assert_eq!(sequence.clone().skip(n).take(1).collect::<Vec<_>>(), [sequence.n(n as u64)]);
+        assert_eq!(
+            sequence.clone().step_by(n).take(3).collect::<Vec<_>>(),
+            [sequence.n(0), sequence.n(n as u64), sequence.n(2 * n as u64)]
+        );
+        assert_eq!(sequence.clone().last(), Some(sequence.n(u64::MAX)));
+
+        let mut sequence = sequence;
+        sequence.set_index(u64::MAX - 1);
+        assert_eq!(sequence.clone().count(), 2);
+        assert_eq!(sequence.nth(1), Some(sequence.n(u64::MAX)));
+        assert!(sequence.exhausted());
+        assert_eq!(sequence.nth(1), None);
+        assert_eq!(sequence.nth_back(0), None);
+
+        let mut sequence = RandomSequence::<u64>::new(3, 4);
+        assert_eq!(sequence.nth_back(n), Some(sequence.n(u64::MAX - n as u64)));
+        assert_eq!(sequence.next_back(), Some(sequence.n(u64::MAX - 1 - n as u64)));
+        assert_eq!(sequence.index(), Some(0));
+
+        let sequence = RandomSequence::<u128>::new(3, 4);
+        assert_eq!(sequence.clone().nth(usize::MAX), Some(sequence.n(usize::MAX as u128)));
+    }
+
+    #[test]
+    fn test_shard_exhaustion() {
+        let sequence = RandomSequenceBuilder::<u16>::new(0, 0).with_len(10).into_iter();
+        let shards = sequence.split::<4>();
+        assert_eq!(shards.clone().map(|shard| shard.len()), [3, 3, 2, 2]);
+        assert_eq!(shards.clone().map(|shard| shard.index()), [Some(0), Some(3), Some(6), Some(8)]);
+
+        let mut shard = shards[1].clone();
+        assert_eq!(shard.fill(&mut [0; 10]), 3);
+        assert!(shard.exhausted());
+        assert_eq!(shard.next(), None);
+        assert_eq!(shard.len(), 0);
+        shard.set_index(4);
+        assert_eq!(shard.next(), Some(sequence.n(4)));
+
+        // more shards than indices leaves the trailing shards empty
+        let shards = RandomSequenceBuilder::<u8>::new(0, 0).with_len(2).into_iter().split::<3>();
+        assert_eq!(shards.clone().map(|shard| shard.len()), [1, 1, 0]);
+        assert!(shards[2].exhausted());
+        let mut cursor = shards[2].clone().into_cursor();
+        assert_eq!(cursor.prev(), None);
+        assert_eq!(cursor.next(), None);
+        assert_eq!(shards[2].shard(0, 2).len(), 0);
+        assert_eq!(shards[2].clone().next_back(), None);
+
+        // the full range of the type
+        let shards = RandomSequence::<u128>::new(0, 0).split::<2>();
+        assert_eq!(shards.map(|shard| shard.index()), [Some(0), Some(1 << 127)]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_shard_set_index_out_of_bounds() {
+        let mut shard = RandomSequenceBuilder::<u16>::new(0, 0).with_len(100).into_iter().shard(1, 2);
+        shard.set_index(49);
+    }
+
+    #[test]
+    #[should_panic(expected = "RandomSequence index out of bounds")]
+    fn test_empty_shard_set_index() {
+        let mut shard = RandomSequenceBuilder::<u8>::new(0, 0).with_len(2).into_iter().shard(2, 3);
+        shard.set_index(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "RandomSequence shard is empty")]
+    fn test_empty_shard_wrapping_next() {
+        let shard = RandomSequenceBuilder::<u8>::new(0, 0).with_len(2).into_iter().shard(2, 3);
+        shard.into_cursor().wrapping_next();
+    }
+
+    #[test]
+    #[should_panic(expected = "RandomSequence shard is empty")]
+    fn test_empty_shard_wrapping_prev() {
+        let shard = RandomSequenceBuilder::<u8>::new(0, 0).with_len(2).into_iter().shard(2, 3);
+        shard.into_cursor().wrapping_prev();
+    }
+
+    /// The outputs must not change between versions for the same builder.
+    #[test]
+    fn test_stable_outputs() {
+        macro_rules! assert_outputs {
+            ($type:ident, $expected:expr) => {
+                let sequence = RandomSequence::<$type>::new(0x12, 0x56);
+                let outputs: Vec<$type> = (0..4).map(|i| sequence.n(i)).collect();
+                assert_eq!(outputs, $expected);
+            };
+        }
+
+        assert_outputs!(u8, [189, 179, 5, 4]);
+        assert_outputs!(u16, [6687, 12919, 22207, 33215]);
+        assert_outputs!(u32, [1242719261, 4275219367, 2031000315, 4113064817]);
+        assert_outputs!(u64, [8341153947298464206, 14770462411529459416, 2127604784217753588, 2618757050545733883]);
+        #[cfg(target_pointer_width = "64")]
+        assert_outputs!(usize, [8341153947298464206, 14770462411529459416, 2127604784217753588, 2618757050545733883]);
+        assert_outputs!(
+            u128,
+            [
+                177647753893615830856832439270362091424,
+                201314375328804832075295653321000388804,
+                232937556469195838143612987681366808218,
+                135436668135781555963426958672794564782,
+            ]
+        );
+    }
+
     macro_rules! test_distribution {
         ($name:ident, $type:ident, $check:literal) => {
             #[ignore]  // ChiSquared p value is too unreliable
@@ -374,5 +1225,6 @@ mod tests {
     test_distribution!(test_u16_distribution, u16, 65536);
     test_distribution!(test_u32_distribution, u32, 100_000);
     test_distribution!(test_u64_distribution, u64, 100_000);
+    test_distribution!(test_u128_distribution, u128, 100_000);
     test_distribution!(test_usize_distribution, usize, 100_000);
 }
@@ -0,0 +1,489 @@
+use core::fmt::{Debug, Formatter};
+use core::iter::FusedIterator;
+use core::ops::{Bound, RangeBounds};
+
+use num_traits::{NumCast, One, ToPrimitive, Zero};
+
+use crate::builder::SequenceInt;
+use crate::permutation::{Permutation, Qpr};
+use crate::sequence::RandomSequence;
+
+/// A view of a contiguous range of indices of a [RandomSequence], created by
+/// [RandomSequence::range] and [RandomSequence::page].
+///
+/// The slice iterates from either end over its remaining elements, which are indexed from zero with
+/// [RandomSequenceSlice::get]. Computing any element is O(1), and cloning the slice is cheap as it
+/// borrows the sequence.
+///
+/// ```
+/// use rand_unique::RandomSequence;
+///
+/// let sequence = RandomSequence::<u16>::new(1, 2);
+///
+/// // the third page of 20 elements, indices 40 to 59
+/// let page = sequence.page(2, 20);
+/// assert_eq!(page.len(), 20);
+/// assert_eq!(page.get(0), Some(sequence.n(40)));
+/// assert!(page.clone().eq(sequence.range(40..60)));
+/// assert_eq!(page.rev().next(), Some(sequence.n(59)));
+/// ```
+pub struct RandomSequenceSlice<'a, T, P = Qpr<T>>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+    sequence: &'a RandomSequence<T, P>,
+
+    /// The next indices returned from the front and back, within `[0, len)` of the sequence.
+    front: T::Unsigned,
+    back: T::Unsigned,
+
+    /// Marks the slice as empty, as `front > back` can't be represented for a slice ending at 0.
+    ended: bool,
+}
+
+impl<'a, T, P> RandomSequenceSlice<'a, T, P>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+    /// A slice of the indices `[front, back]`, or an empty slice.
+    #[inline]
+    fn new(sequence: &'a RandomSequence<T, P>, range: Option<(T::Unsigned, T::Unsigned)>) -> Self {
+        match range {
+            Some((front, back)) => Self {
+                sequence,
+                front,
+                back,
+                ended: false,
+            },
+            None => Self {
+                sequence,
+                front: T::Unsigned::zero(),
+                back: T::Unsigned::zero(),
+                ended: true,
+            },
+        }
+    }
+
+    /// Get the element `index` places after the front of the slice, or `None` if the slice is
+    /// shorter.
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<T> {
+        if self.ended {
+            return None;
+        }
+        let index: T::Unsigned = NumCast::from(index)?;
+        match index <= self.back - self.front {
+            true => Some(self.sequence.n_unsigned(self.front + index)),
+            false => None,
+        }
+    }
+
+    /// Check if there are no remaining elements in the slice.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.ended
+    }
+
+    /// The sequence the slice is a view of.
+    #[inline]
+    pub fn sequence(&self) -> &'a RandomSequence<T, P> {
+        self.sequence
+    }
+
+    /// The number of remaining elements, `None` if it overflows `usize`. Available for every type,
+    /// as with [RandomSequence::remaining].
+    #[inline]
+    pub fn remaining(&self) -> Option<usize> {
+        if self.ended {
+            return Some(0);
+        }
+        (self.back - self.front).to_usize()?.checked_add(1)
+    }
+
+    /// Drop `n` elements from the front, or from the back, returning whether any remain.
+    #[inline]
+    fn advance(&mut self, n: usize, from_back: bool) -> bool {
+        if self.ended {
+            return false;
+        }
+        match (self.back - self.front).to_usize() {
+            Some(distance) if n > distance => {
+                self.ended = true;
+                false
+            }
+            _ => {
+                let n: T::Unsigned = NumCast::from(n).unwrap();
+                match from_back {
+                    true => self.back = self.back - n,
+                    false => self.front = self.front + n,
+                }
+                true
+            }
+        }
+    }
+}
+
+impl<T, P> Clone for RandomSequenceSlice<'_, T, P>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+    #[inline]
+    fn clone(&self) -> Self {
+        Self { ..*self }
+    }
+}
+
+impl<T, P> Debug for RandomSequenceSlice<'_, T, P>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+    RandomSequence<T, P>: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("RandomSequenceSlice")
+            .field("sequence", &self.sequence)
+            .field("len", &self.remaining())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, P> Iterator for RandomSequenceSlice<'_, T, P>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.ended {
+            return None;
+        }
+        let next = self.sequence.n_unsigned(self.front);
+        match self.front == self.back {
+            true => self.ended = true,
+            false => self.front = self.front + T::Unsigned::one(),
+        }
+        Some(next)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.remaining() {
+            Some(remaining) => (remaining, Some(remaining)),
+            None => (usize::MAX, None),
+        }
+    }
+
+    #[inline]
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        match self.advance(n, false) {
+            true => self.next(),
+            false => None,
+        }
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.remaining().expect("RandomSequenceSlice count overflows usize")
+    }
+
+    #[inline]
+    fn last(self) -> Option<Self::Item> {
+        match self.ended {
+            false => Some(self.sequence.n_unsigned(self.back)),
+            true => None,
+        }
+    }
+}
+
+impl<T, P> DoubleEndedIterator for RandomSequenceSlice<'_, T, P>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.ended {
+            return None;
+        }
+        let next = self.sequence.n_unsigned(self.back);
+        match self.back == self.front {
+            true => self.ended = true,
+            false => self.back = self.back - T::Unsigned::one(),
+        }
+        Some(next)
+    }
+
+    #[inline]
+    fn nth_back(&mut self, n: usize) -> Option<Self::Item> {
+        match self.advance(n, true) {
+            true => self.next_back(),
+            false => None,
+        }
+    }
+}
+
+macro_rules! impl_exact_size_iterator {
+    ($T:ident) => {
+        impl<P> ExactSizeIterator for RandomSequenceSlice<'_, $T, P> where P: Permutation<$T> {}
+    };
+}
+
+// Can only fit exact size iterators in types smaller than usize, as for RandomSequence.
+impl_exact_size_iterator!(u8);
+impl_exact_size_iterator!(u16);
+#[cfg(target_pointer_width = "64")]
+impl_exact_size_iterator!(u32);
+impl_exact_size_iterator!(i8);
+impl_exact_size_iterator!(i16);
+#[cfg(target_pointer_width = "64")]
+impl_exact_size_iterator!(i32);
+
+impl<T, P> FusedIterator for RandomSequenceSlice<'_, T, P>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+}
+
+impl<T, P> RandomSequence<T, P>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+    /// Get a view of the elements at a range of indices, `n(start)..n(end)`.
+    ///
+    /// The range uses the indices of the whole sequence, as with [RandomSequence::n], ignoring the
+    /// cursors and any shard. Negative indices count backwards from the end of the sequence, and an
+    /// unbounded end includes the final index of a sequence of `T::MAX + 1` elements, which `T`
+    /// can't exclude.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an index is outside of a sequence bounded with
+    /// [RandomSequenceBuilder::with_len](crate::RandomSequenceBuilder::with_len), or if the range
+    /// starts after it ends.
+    #[inline]
+    pub fn range(&self, range: impl RangeBounds<T>) -> RandomSequenceSlice<'_, T, P> {
+        let max = self.max_index();
+
+        // None if the start is past the final index, or if the end is before the first index
+        let start = match range.start_bound() {
+            Bound::Included(&start) if self.is_range_end(start) => None,
+            Bound::Included(&start) => Some(self.range_index(start)),
+            Bound::Excluded(&start) => {
+                Some(self.range_index(start)).filter(|&start| start < max).map(|start| start + T::Unsigned::one())
+            }
+            Bound::Unbounded => Some(T::Unsigned::zero()),
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => Some(self.range_index(end)),
+            Bound::Excluded(&end) if self.is_range_end(end) => Some(max),
+            Bound::Excluded(&end) => {
+                Some(self.range_index(end)).filter(|end| !end.is_zero()).map(|end| end - T::Unsigned::one())
+            }
+            Bound::Unbounded => Some(max),
+        };
+
+        let range = match (start, end) {
+            (Some(start), Some(end)) if start <= end => Some((start, end)),
+            (Some(start), Some(end)) if start == end + T::Unsigned::one() => None,
+            (None, Some(end)) if end == max => None,
+            (Some(start), None) if start.is_zero() => None,
+            _ => panic!("RandomSequence range starts after it ends"),
+        };
+        RandomSequenceSlice::new(self, range)
+    }
+
+    /// Get a view of page `page` of the sequence, with `size` elements per page, the indices
+    /// `page * size..(page + 1) * size`.
+    ///
+    /// The final page is shorter if the length of the sequence isn't a multiple of `size`, and pages
+    /// after it are empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero.
+    #[inline]
+    pub fn page(&self, page: usize, size: usize) -> RandomSequenceSlice<'_, T, P> {
+        assert!(size > 0, "RandomSequence page size must be positive");
+
+        // neither the product nor the sum overflow, as usize is at most 64 bits
+        let max = self.max_index().to_u128().unwrap();
+        let start = page as u128 * size as u128;
+        let end = (start + (size as u128 - 1)).min(max);
+        let range = match start <= max {
+            true => Some((NumCast::from(start).unwrap(), NumCast::from(end).unwrap())),
+            false => None,
+        };
+        RandomSequenceSlice::new(self, range)
+    }
+
+    /// Check if an index of a range is the length of a bounded sequence, one past the final index.
+    #[inline]
+    fn is_range_end(&self, index: T) -> bool {
+        index >= T::zero() && Some(index.to_unsigned()) == self.config.len
+    }
+
+    /// Convert an index of a range into a position within `[0, len)`.
+    #[inline]
+    fn range_index(&self, index: T) -> T::Unsigned {
+        match self.config.len {
+            None => index.to_unsigned(),
+            Some(len) => index.checked_index(len).expect("RandomSequence range out of bounds"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::RandomSequenceBuilder;
+
+    macro_rules! test_range {
+        ($name:ident, $type:ident) => {
+            #[test]
+            fn $name() {
+                let config = RandomSequenceBuilder::<$type>::new(0x12, 0x56);
+                for sequence in [config.with_len(1).into_iter(), config.with_len(100).into_iter()] {
+                    let len = sequence.size_hint().0 as $type;
+                    let nums: Vec<$type> = sequence.clone().collect();
+
+                    for (start, end) in [(0, len), (0, 0), (len, len), (0, 1), (len - 1, len), (len / 3, len / 2)] {
+                        let expected = &nums[start as usize..end as usize];
+                        let slice = sequence.range(start..end);
+                        assert_eq!(slice.size_hint(), (expected.len(), Some(expected.len())));
+                        assert_eq!(slice.is_empty(), expected.is_empty());
+                        assert!(slice.clone().eq(expected.iter().copied()));
+                        assert!(slice.clone().rev().eq(expected.iter().rev().copied()));
+                        for i in 0..=expected.len() {
+                            assert_eq!(slice.get(i), expected.get(i).copied());
+                            assert_eq!(slice.clone().nth(i), expected.get(i).copied());
+                            assert_eq!(slice.clone().nth_back(i), expected.iter().rev().nth(i).copied());
+                        }
+                        assert_eq!(slice.clone().count(), expected.len());
+                        assert_eq!(slice.clone().last(), expected.last().copied());
+
+                        // the remaining elements are indexed from the front
+                        let mut slice = slice;
+                        if slice.next().is_some() {
+                            assert_eq!(slice.get(0), expected.get(1).copied());
+                            assert_eq!(slice.size_hint().0, expected.len() - 1);
+                        }
+                    }
+
+                    // meet in the middle
+                    let mut slice = sequence.range(..);
+                    let mut front = Vec::new();
+                    let mut back = Vec::new();
+                    while let Some(num) = slice.next() {
+                        front.push(num);
+                        back.extend(slice.next_back());
+                        assert_eq!(slice.size_hint().0, nums.len() - front.len() - back.len());
+                    }
+                    assert!(front.iter().chain(back.iter().rev()).eq(nums.iter()));
+                    assert_eq!(slice.next_back(), None);
+
+                    // bounds
+                    assert!(sequence.range(..=len - 1).eq(nums.iter().copied()));
+                    assert!(sequence.range((Bound::Excluded(0), Bound::Unbounded)).eq(nums[1..].iter().copied()));
+                    assert!(sequence.range(len..).is_empty());
+
+                    // pages, with a shorter final page and empty pages after it
+                    for size in [1, 7, 100, 1000] {
+                        let pages: Vec<$type> = (0..)
+                            .map(|page| sequence.page(page, size))
+                            .take_while(|page| !page.is_empty())
+                            .flatten()
+                            .collect();
+                        assert_eq!(pages, nums);
+                        assert!(sequence.page((nums.len() + size - 1) / size, size).is_empty());
+                        assert!(sequence.page(usize::MAX, size).is_empty());
+                    }
+                }
+
+                // the full domain, with T::MAX + 1 elements
+                let sequence = config.into_iter();
+                let slice = sequence.range(..);
+                assert_eq!(slice.clone().next(), Some(sequence.n(0)));
+                assert_eq!(slice.clone().next_back(), Some(sequence.n($type::MAX)));
+                assert_eq!(sequence.range($type::MAX..).collect::<Vec<_>>(), [sequence.n($type::MAX)]);
+                assert_eq!(sequence.range(..=$type::MAX).last(), Some(sequence.n($type::MAX)));
+                assert_eq!(sequence.range($type::MAX..=$type::MAX).count(), 1);
+                assert!(sequence.range((Bound::Excluded($type::MAX), Bound::Unbounded)).is_empty());
+
+                let last_page = sequence.page(usize::MAX, usize::MAX);
+                match ($type::MAX as u128)
+                    .checked_add(1)
+                    .map_or(true, |len| len > usize::MAX as u128 * usize::MAX as u128)
+                {
+                    true => assert_eq!(
+                        last_page.get(0),
+                        Some(sequence.n((usize::MAX as u128 * usize::MAX as u128) as $type))
+                    ),
+                    false => assert!(last_page.is_empty()),
+                }
+                let page = sequence.page(3, 10);
+                assert!(page.eq((30..40).map(|i| sequence.n(i))));
+            }
+        };
+    }
+
+    test_range!(test_u8_range, u8);
+    test_range!(test_u16_range, u16);
+    test_range!(test_u32_range, u32);
+    test_range!(test_u64_range, u64);
+    test_range!(test_u128_range, u128);
+    test_range!(test_usize_range, usize);
+
+    #[test]
+    fn test_signed_range() {
+        let sequence = RandomSequenceBuilder::<i16>::new(1, 2).with_range(-500..500).into_iter();
+        let nums: Vec<i16> = sequence.clone().collect();
+        assert!(sequence.range(..1000).eq(nums.iter().copied()));
+        assert!(sequence.range(-10..).eq(nums[990..].iter().copied()));
+        assert!(sequence.range(..-1).eq(nums[..999].iter().copied()));
+
+        let sequence = RandomSequence::<i8>::new(1, 2);
+        assert!(sequence.range(-2..).eq([sequence.n(-2), sequence.n(-1)]));
+        assert_eq!(sequence.range((Bound::Included(0), Bound::Included(-1))).len(), 256);
+    }
+
+    #[test]
+    fn test_large_range() {
+        // the length only overflows usize for the full domain of a 64-bit type
+        let sequence = RandomSequence::<u64>::new(1, 2);
+        let slice = sequence.range(1..);
+        assert_eq!(slice.size_hint(), (usize::MAX, Some(usize::MAX)));
+        assert_eq!(slice.remaining(), Some(usize::MAX));
+        assert_eq!(sequence.range(..).remaining(), None);
+        assert_eq!(sequence.page(3, 10).remaining(), Some(10));
+        assert_eq!(sequence.range(..).size_hint(), (usize::MAX, None));
+        assert_eq!(slice.clone().nth(usize::MAX - 1), Some(sequence.n(u64::MAX)));
+        assert_eq!(slice.get(1_000_000_000_000), Some(sequence.n(1_000_000_000_001)));
+    }
+
+    #[test]
+    #[should_panic(expected = "RandomSequence range starts after it ends")]
+    fn test_range_reversed() {
+        RandomSequence::<u32>::new(1, 2).range((Bound::Included(10), Bound::Excluded(5)));
+    }
+
+    #[test]
+    #[should_panic(expected = "RandomSequence range out of bounds")]
+    fn test_range_out_of_bounds() {
+        RandomSequenceBuilder::<u32>::new(1, 2).with_len(100).into_iter().range(..101);
+    }
+
+    #[test]
+    #[should_panic(expected = "RandomSequence page size must be positive")]
+    fn test_page_size_zero() {
+        RandomSequence::<u32>::new(1, 2).page(0, 0);
+    }
+}
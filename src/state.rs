@@ -0,0 +1,737 @@
+//! Persisting the state of a [RandomSequence], to checkpoint and resume iteration.
+//!
+//! The state is the config, the cursor, the shard boundaries, and the exhaustion flag. Everything
+//! else is recomputed from the config on load, and checked against any stored values, so that a
+//! state which is inconsistent with its config is rejected rather than producing a different
+//! sequence.
+
+use core::fmt::{Display, Formatter};
+use core::mem::size_of;
+use core::str::FromStr;
+
+use num_traits::{NumCast, One, ToPrimitive, Zero};
+
+use crate::builder::{RandomSequenceBuilder, SequenceInt};
+use crate::permutation::{Permutation, Qpr};
+use crate::sequence::RandomSequence;
+
+/// The version of the binary encoding, the first byte of every encoded state. Changes with
+/// [RandomSequenceBuilder::ALGORITHM_VERSION], or with the layout of the encoding.
+const ENCODING_VERSION: u8 = 1;
+
+/// The flags byte of the binary encoding.
+const FLAG_ENDED: u8 = 1;
+const FLAG_BOUNDED: u8 = 1 << 1;
+
+/// The number of integers in the config, in both the binary encoding and the string form.
+const CONFIG_WORDS: usize = 8;
+
+/// The number of integers in the binary encoding, after the version and flags bytes.
+const ENCODED_WORDS: usize = CONFIG_WORDS + 4;
+
+/// The prefix of the string form of a [RandomSequenceBuilder], followed by the algorithm version.
+const STRING_PREFIX: &str = "ru";
+
+/// The reasons a stored [RandomSequence] state or [RandomSequenceBuilder] can be rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum StateError {
+    /// The encoding has the wrong length or integer type, or unknown flags.
+    Encoding,
+
+    /// The data was produced by a version of the algorithm which generates a different sequence.
+    Version,
+
+    /// The prime is not a prime number satisfying `prime = 3 mod 4` within the domain.
+    Prime,
+
+    /// The length is zero, or a parameter is outside of the domain of the sequence.
+    Domain,
+
+    /// The stored permutation or start index don't match those computed from the config.
+    Permutation,
+
+    /// The cursor or the shard boundaries are outside of the sequence.
+    Cursor,
+}
+
+impl Display for StateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            StateError::Encoding => "invalid RandomSequence encoding",
+            StateError::Version => "RandomSequence algorithm version is not supported",
+            StateError::Prime => "RandomSequence prime is not a 3 mod 4 prime within the domain",
+            StateError::Domain => "RandomSequence parameters are outside of the domain",
+            StateError::Permutation => "RandomSequence permutation does not match the config",
+            StateError::Cursor => "RandomSequence cursor is outside of the sequence",
+        })
+    }
+}
+
+impl<T> RandomSequenceBuilder<T>
+where
+    T: SequenceInt,
+{
+    /// The version of the algorithm which generates a sequence from its config, recorded in the
+    /// serialized config and the string form.
+    ///
+    /// A config from another version is rejected with [StateError::Version] unless it generates the
+    /// same sequence in this version. Configs serialized before the version was recorded, by v0.3 and
+    /// earlier, are read as version 1.
+    pub const ALGORITHM_VERSION: u32 = 1;
+
+    /// Check a config from `version` of the algorithm generates the same sequence in this version.
+    #[inline]
+    pub(crate) fn check_version(version: u32) -> Result<(), StateError> {
+        match version {
+            1 => Ok(()),
+            _ => Err(StateError::Version),
+        }
+    }
+
+    /// Check the config defines a unique sequence, as a deserialized config can hold any values.
+    #[inline]
+    pub(crate) fn validate(&self) -> Result<(), StateError> {
+        if self.len.is_some_and(|len| len.is_zero()) {
+            return Err(StateError::Domain);
+        }
+        let mask = self.mask();
+        if self.prime > mask || !Self::is_prime_3_mod_4(self.prime) {
+            return Err(StateError::Prime);
+        }
+        if self.init_base > mask || self.init_offset > mask || self.intermediate_xor > mask {
+            return Err(StateError::Domain);
+        }
+        Ok(())
+    }
+
+    /// The integers of the config, with a zero `len` for an unbounded sequence.
+    #[inline]
+    fn to_words(self) -> [T::Unsigned; CONFIG_WORDS] {
+        [
+            self.seed_base.to_unsigned(),
+            self.seed_offset.to_unsigned(),
+            self.init_base,
+            self.init_offset,
+            self.prime,
+            self.intermediate_xor,
+            self.len.unwrap_or_else(T::Unsigned::zero),
+            self.min.to_unsigned(),
+        ]
+    }
+
+    /// The config from [RandomSequenceBuilder::to_words].
+    #[inline]
+    fn from_words(words: [T::Unsigned; CONFIG_WORDS]) -> Self {
+        let [seed_base, seed_offset, init_base, init_offset, prime, intermediate_xor, len, min] = words;
+        Self {
+            seed_base: T::from_unsigned(seed_base),
+            seed_offset: T::from_unsigned(seed_offset),
+            init_base,
+            init_offset,
+            prime,
+            intermediate_xor,
+            len: (!len.is_zero()).then_some(len),
+            min: T::from_unsigned(min),
+        }
+    }
+}
+
+/// The string form, `ru<version>:<type>:<params>`, where the params are the fixed width lowercase hex
+/// of `seed_base`, `seed_offset`, `init_base`, `init_offset`, `prime`, `intermediate_xor`, `len`
+/// (zero for an unbounded sequence), and `min`.
+impl<T> Display for RandomSequenceBuilder<T>
+where
+    T: SequenceInt,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}{}:{}:", STRING_PREFIX, Self::ALGORITHM_VERSION, T::NAME)?;
+        for word in self.to_words() {
+            write!(f, "{:01$x}", word.to_u128().unwrap(), 2 * size_of::<T>())?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse the string form from [Display], rejecting a config from an unsupported algorithm version,
+/// for another integer type, or which doesn't define a unique sequence.
+impl<T> FromStr for RandomSequenceBuilder<T>
+where
+    T: SequenceInt,
+{
+    type Err = StateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (version, rest) =
+            s.strip_prefix(STRING_PREFIX).and_then(|s| s.split_once(':')).ok_or(StateError::Encoding)?;
+        let version = match version.bytes().all(|b| b.is_ascii_digit()) {
+            true => version.parse().map_err(|_| StateError::Encoding)?,
+            false => return Err(StateError::Encoding),
+        };
+        Self::check_version(version)?;
+
+        let (name, params) = rest.split_once(':').ok_or(StateError::Encoding)?;
+        let width = 2 * size_of::<T>();
+        if name != T::NAME || params.len() != CONFIG_WORDS * width || !params.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(StateError::Encoding);
+        }
+
+        let mut words = [T::Unsigned::zero(); CONFIG_WORDS];
+        for (i, word) in words.iter_mut().enumerate() {
+            let hex =
+                u128::from_str_radix(&params[i * width..(i + 1) * width], 16).map_err(|_| StateError::Encoding)?;
+            *word = NumCast::from(hex).ok_or(StateError::Encoding)?;
+        }
+        let config = Self::from_words(words);
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+impl<T, P> RandomSequence<T, P>
+where
+    T: SequenceInt,
+    P: Permutation<T>,
+{
+    /// The length of the binary encoding from [RandomSequence::encode].
+    pub const ENCODED_LEN: usize = 2 + ENCODED_WORDS * size_of::<T>();
+
+    /// Write the state of the sequence to `out` in a compact binary encoding, returning the number of
+    /// bytes written, [RandomSequence::ENCODED_LEN].
+    ///
+    /// The state is the config, the front and back cursors, the shard boundaries, and the exhaustion
+    /// flag, such that
+    /// [RandomSequence::decode] resumes the sequence exactly where it left off. The permutation is
+    /// not included, and is rebuilt from the config, or passed to [RandomSequence::decode_with].
+    ///
+    /// The encoding is a version byte, a flags byte, and then each integer in little endian:
+    /// `seed_base`, `seed_offset`, `init_base`, `init_offset`, `prime`, `intermediate_xor`, `len`,
+    /// `min`, `current_index`, `back_index`, `first_index`, and `last_index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `out` is shorter than [RandomSequence::ENCODED_LEN].
+    #[inline]
+    pub fn encode(&self, out: &mut [u8]) -> usize {
+        assert!(out.len() >= Self::ENCODED_LEN, "out must be at least ENCODED_LEN bytes");
+
+        let mut flags = 0;
+        if self.ended {
+            flags |= FLAG_ENDED;
+        }
+        if self.config.len.is_some() {
+            flags |= FLAG_BOUNDED;
+        }
+        out[0] = ENCODING_VERSION;
+        out[1] = flags;
+
+        let mut words = [T::Unsigned::zero(); ENCODED_WORDS];
+        words[..CONFIG_WORDS].copy_from_slice(&self.config.to_words());
+        words[CONFIG_WORDS..].copy_from_slice(&[
+            self.current_index,
+            self.back_index,
+            self.first_index,
+            self.last_index,
+        ]);
+        for (word, out) in words.iter().zip(out[2..Self::ENCODED_LEN].chunks_exact_mut(size_of::<T>())) {
+            out.copy_from_slice(&word.to_u128().unwrap().to_le_bytes()[..size_of::<T>()]);
+        }
+        Self::ENCODED_LEN
+    }
+
+    /// Restore a sequence from the encoding of [RandomSequence::encode], with `permutation` in place
+    /// of the default permutation, as with [RandomSequenceBuilder::into_iter_with].
+    #[inline]
+    pub fn decode_with(bytes: &[u8], permutation: P) -> Result<Self, StateError> {
+        Self::decode_state(bytes, |config| config.into_iter_with(permutation))
+    }
+
+    /// Decode the state, and restore it onto the sequence built from the decoded config.
+    #[inline]
+    fn decode_state(bytes: &[u8], build: impl FnOnce(RandomSequenceBuilder<T>) -> Self) -> Result<Self, StateError> {
+        if bytes.len() != Self::ENCODED_LEN {
+            return Err(StateError::Encoding);
+        }
+        if bytes[0] != ENCODING_VERSION {
+            return Err(StateError::Version);
+        }
+        if bytes[1] & !(FLAG_ENDED | FLAG_BOUNDED) != 0 {
+            return Err(StateError::Encoding);
+        }
+
+        let mut words = [T::Unsigned::zero(); ENCODED_WORDS];
+        for (word, bytes) in words.iter_mut().zip(bytes[2..].chunks_exact(size_of::<T>())) {
+            let mut buffer = [0; 16];
+            buffer[..bytes.len()].copy_from_slice(bytes);
+            *word = NumCast::from(u128::from_le_bytes(buffer)).unwrap();
+        }
+        let mut config_words = [T::Unsigned::zero(); CONFIG_WORDS];
+        config_words.copy_from_slice(&words[..CONFIG_WORDS]);
+        let (current_index, back_index) = (words[8], words[9]);
+        let (first_index, last_index) = (words[10], words[11]);
+
+        let config = RandomSequenceBuilder::from_words(config_words);
+        if config.len.is_some() != (bytes[1] & FLAG_BOUNDED != 0) {
+            return Err(StateError::Encoding);
+        }
+        config.validate()?;
+        build(config).with_cursor(current_index, back_index, first_index, last_index, bytes[1] & FLAG_ENDED != 0)
+    }
+
+    /// Restore the cursors, shard boundaries, and exhaustion flag onto a sequence built from its
+    /// config.
+    #[inline]
+    pub(crate) fn with_cursor(
+        mut self,
+        current_index: T::Unsigned,
+        back_index: T::Unsigned,
+        first_index: T::Unsigned,
+        last_index: T::Unsigned,
+        ended: bool,
+    ) -> Result<Self, StateError> {
+        let valid = first_index <= current_index
+            && current_index <= back_index
+            && back_index <= last_index
+            && last_index <= self.max_index();
+        // the empty window of an empty shard
+        let empty = ended && first_index.is_one() && (last_index | current_index | back_index).is_zero();
+        let valid = valid || empty;
+        if !valid {
+            return Err(StateError::Cursor);
+        }
+        self.current_index = current_index;
+        self.back_index = back_index;
+        self.first_index = first_index;
+        self.last_index = last_index;
+        self.ended = ended;
+        Ok(self)
+    }
+}
+
+impl<T> RandomSequence<T, Qpr<T>>
+where
+    T: SequenceInt,
+{
+    /// Restore a sequence from the encoding of [RandomSequence::encode], resuming iteration exactly
+    /// where it left off.
+    ///
+    /// Returns an error if the encoding is invalid, or the state is inconsistent with its config,
+    /// such as a prime which doesn't satisfy `prime = 3 mod 4` or a cursor outside of the sequence.
+    #[inline]
+    pub fn decode(bytes: &[u8]) -> Result<Self, StateError> {
+        Self::decode_state(bytes, |config| config.into_iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::*;
+
+    /// The serialized form of a [RandomSequenceBuilder], tagged with the algorithm version.
+    #[derive(Serialize, Deserialize)]
+    #[serde(rename = "RandomSequenceBuilder")]
+    struct BuilderState<T>
+    where
+        T: SequenceInt,
+    {
+        /// `None` for configs serialized before the version was recorded.
+        #[serde(default)]
+        version: Option<u32>,
+        seed_base: T,
+        seed_offset: T,
+        init_base: T::Unsigned,
+        init_offset: T::Unsigned,
+        prime: T::Unsigned,
+        intermediate_xor: T::Unsigned,
+        #[serde(default)]
+        len: Option<T::Unsigned>,
+        #[serde(default)]
+        min: T,
+    }
+
+    impl<T> Serialize for RandomSequenceBuilder<T>
+    where
+        T: SequenceInt + Serialize,
+        T::Unsigned: Serialize,
+    {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            BuilderState {
+                version: Some(Self::ALGORITHM_VERSION),
+                seed_base: self.seed_base,
+                seed_offset: self.seed_offset,
+                init_base: self.init_base,
+                init_offset: self.init_offset,
+                prime: self.prime,
+                intermediate_xor: self.intermediate_xor,
+                len: self.len,
+                min: self.min,
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de, T> Deserialize<'de> for RandomSequenceBuilder<T>
+    where
+        T: SequenceInt + Deserialize<'de>,
+        T::Unsigned: Deserialize<'de>,
+    {
+        /// Rejects a config from an algorithm version which generates a different sequence, or which
+        /// doesn't define a unique sequence. Configs from before the version was recorded, without
+        /// `len` and `min`, are unbounded version 1.
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let state = BuilderState::<T>::deserialize(deserializer)?;
+            Self::check_version(state.version.unwrap_or(1)).map_err(D::Error::custom)?;
+            let config = Self {
+                seed_base: state.seed_base,
+                seed_offset: state.seed_offset,
+                init_base: state.init_base,
+                init_offset: state.init_offset,
+                prime: state.prime,
+                intermediate_xor: state.intermediate_xor,
+                len: state.len,
+                min: state.min,
+            };
+            config.validate().map_err(D::Error::custom)?;
+            Ok(config)
+        }
+    }
+
+    /// The serialized form of a [RandomSequence].
+    #[derive(Serialize, Deserialize)]
+    #[serde(rename = "RandomSequence")]
+    struct SequenceState<T, P>
+    where
+        T: SequenceInt,
+    {
+        config: RandomSequenceBuilder<T>,
+        permutation: P,
+        start_index: T::Unsigned,
+        current_index: T::Unsigned,
+        back_index: T::Unsigned,
+        first_index: T::Unsigned,
+        last_index: T::Unsigned,
+        ended: bool,
+    }
+
+    impl<T, P> Serialize for RandomSequence<T, P>
+    where
+        T: SequenceInt + Serialize,
+        T::Unsigned: Serialize,
+        P: Permutation<T> + Serialize,
+    {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            SequenceState {
+                config: self.config,
+                permutation: self.permutation.clone(),
+                start_index: self.start_index,
+                current_index: self.current_index,
+                back_index: self.back_index,
+                first_index: self.first_index,
+                last_index: self.last_index,
+                ended: self.ended,
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de, T, P> Deserialize<'de> for RandomSequence<T, P>
+    where
+        T: SequenceInt + Deserialize<'de>,
+        T::Unsigned: Deserialize<'de>,
+        P: Permutation<T> + PartialEq + Deserialize<'de>,
+    {
+        /// Rebuilds the sequence from the config, and rejects a state whose permutation, start index,
+        /// or cursor are inconsistent with it.
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let state = SequenceState::<T, P>::deserialize(deserializer)?;
+
+            let sequence = state.config.into_iter_with(state.permutation.clone());
+            if sequence.permutation != state.permutation || sequence.start_index != state.start_index {
+                return Err(D::Error::custom(StateError::Permutation));
+            }
+            sequence
+                .with_cursor(state.current_index, state.back_index, state.first_index, state.last_index, state.ended)
+                .map_err(D::Error::custom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::format;
+    use std::string::ToString;
+    use std::vec::Vec;
+
+    use super::*;
+
+    macro_rules! test_encode {
+        ($name:ident, $type:ident) => {
+            #[test]
+            fn $name() {
+                let configs = [
+                    RandomSequenceBuilder::<$type>::new(0, 0),
+                    RandomSequenceBuilder::<$type>::new(13, 99).with_len(100),
+                    RandomSequenceBuilder::<$type>::new(7, 3).with_range(10..=20),
+                ];
+                for config in configs {
+                    let mut bytes = [0u8; RandomSequence::<$type>::ENCODED_LEN];
+
+                    // resume mid-iteration, after the sequence has been advanced from both ends
+                    let mut sequence = config.into_iter();
+                    sequence.next();
+                    sequence.next();
+                    assert_eq!(sequence.encode(&mut bytes), bytes.len());
+                    let decoded = RandomSequence::<$type>::decode(&bytes).unwrap();
+                    assert_eq!(decoded.index(), sequence.index());
+                    let expected: Vec<$type> = sequence.clone().take(50).collect();
+                    let resumed: Vec<$type> = decoded.take(50).collect();
+                    assert_eq!(resumed, expected);
+
+                    // resume a shard, keeping its boundaries
+                    let mut shard = config.into_iter().shard(1, 3);
+                    shard.next();
+                    shard.encode(&mut bytes);
+                    let decoded = RandomSequence::<$type>::decode(&bytes).unwrap();
+                    assert_eq!(decoded.index(), shard.index());
+                    assert!(decoded.take(50).eq(shard.take(50)));
+
+                    // an exhausted sequence stays exhausted
+                    let mut sequence = config.into_iter().shard(2, 3);
+                    sequence.current_index = sequence.last_index;
+                    sequence.next();
+                    sequence.encode(&mut bytes);
+                    let mut decoded = RandomSequence::<$type>::decode(&bytes).unwrap();
+                    assert!(decoded.exhausted());
+                    assert_eq!(decoded.index(), None);
+                    assert_eq!(decoded.next(), None);
+                    assert_eq!(decoded.next_back(), None);
+                    assert_eq!(decoded.into_cursor().prev(), sequence.into_cursor().prev());
+
+                    // an empty shard stays empty
+                    let shard = config.with_len(2).into_iter().shard(2, 3);
+                    shard.encode(&mut bytes);
+                    let decoded = RandomSequence::<$type>::decode(&bytes).unwrap();
+                    assert!(decoded.is_empty_shard());
+                    assert_eq!(decoded.count(), 0);
+                }
+            }
+        };
+    }
+
+    test_encode!(test_u8_encode, u8);
+    test_encode!(test_u16_encode, u16);
+    test_encode!(test_u32_encode, u32);
+    test_encode!(test_u64_encode, u64);
+    test_encode!(test_u128_encode, u128);
+    test_encode!(test_usize_encode, usize);
+    test_encode!(test_i8_encode, i8);
+    test_encode!(test_i64_encode, i64);
+
+    macro_rules! test_string_form {
+        ($name:ident, $type:ident, $other:ident) => {
+            #[test]
+            fn $name() {
+                let configs = [
+                    RandomSequenceBuilder::<$type>::new(0, 0),
+                    RandomSequenceBuilder::<$type>::new($type::MAX, $type::MIN).with_len(100),
+                    RandomSequenceBuilder::<$type>::new(7, 3).with_range(10..=20),
+                ];
+                for config in configs {
+                    let string = config.to_string();
+                    let prefix = format!("ru1:{}:", stringify!($type));
+                    assert!(string.starts_with(&prefix), "{}", string);
+                    assert_eq!(string.len(), prefix.len() + 16 * core::mem::size_of::<$type>());
+                    assert_eq!(string.parse::<RandomSequenceBuilder<$type>>(), Ok(config));
+
+                    // the params are only valid for their own integer type
+                    let other = string.replacen(stringify!($type), stringify!($other), 1);
+                    assert_eq!(other.parse::<RandomSequenceBuilder<$other>>(), Err(StateError::Encoding));
+                    assert_eq!(other.parse::<RandomSequenceBuilder<$type>>(), Err(StateError::Encoding));
+                }
+            }
+        };
+    }
+
+    test_string_form!(test_u8_string_form, u8, u16);
+    test_string_form!(test_u16_string_form, u16, u8);
+    test_string_form!(test_u32_string_form, u32, u64);
+    test_string_form!(test_u64_string_form, u64, u32);
+    test_string_form!(test_u128_string_form, u128, u64);
+    test_string_form!(test_usize_string_form, usize, u8);
+    test_string_form!(test_i8_string_form, i8, u16);
+    test_string_form!(test_i64_string_form, i64, i32);
+
+    #[test]
+    fn test_string_form_invalid() {
+        let config = RandomSequenceBuilder::<u8>::new(3, 9);
+        let string = config.to_string();
+        assert_eq!(
+            string,
+            format!(
+                "ru1:u8:0309{:02x}{:02x}{:02x}{:02x}0000",
+                config.init_base, config.init_offset, config.prime, config.intermediate_xor
+            )
+        );
+
+        // an unsupported algorithm version is rejected, rather than generating a different sequence
+        assert_eq!(string.replacen("ru1", "ru2", 1).parse::<RandomSequenceBuilder<u8>>(), Err(StateError::Version));
+        assert_eq!(string.replacen("ru1", "ru0", 1).parse::<RandomSequenceBuilder<u8>>(), Err(StateError::Version));
+
+        for invalid in [
+            "",
+            "ru1",
+            "ru1:u8",
+            "ru:u8:0309",
+            "ru+1:u8:03090b0ff30b0000",
+            "xx1:u8:03090b0ff30b0000",
+            "ru1:u8:03090b0ff30b000",
+            "ru1:u8:03090b0ff30b00000",
+            "ru1:u8:+3090b0ff30b0000",
+            "ru1:u8:03090b0ff30b00zz",
+        ] {
+            assert_eq!(invalid.parse::<RandomSequenceBuilder<u8>>(), Err(StateError::Encoding), "{}", invalid);
+        }
+
+        // the params must define a unique sequence
+        assert_eq!("ru1:u8:00000000fb000000".parse::<RandomSequenceBuilder<u8>>().unwrap().prime, 251);
+        assert_eq!("ru1:u8:00000000f1000000".parse::<RandomSequenceBuilder<u8>>(), Err(StateError::Prime));
+        assert_eq!("ru1:u8:000000000b101000".parse::<RandomSequenceBuilder<u8>>(), Err(StateError::Domain));
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        let mut sequence = RandomSequenceBuilder::<u32>::new(5, 8).with_len(1000).into_iter();
+        sequence.next();
+        let mut bytes = [0u8; RandomSequence::<u32>::ENCODED_LEN];
+        sequence.encode(&mut bytes);
+        assert!(RandomSequence::<u32>::decode(&bytes).is_ok());
+
+        // replace the little endian word at index i
+        let with_word = |i: usize, word: u32| {
+            let mut bytes = bytes;
+            bytes[2 + 4 * i..6 + 4 * i].copy_from_slice(&word.to_le_bytes());
+            RandomSequence::<u32>::decode(&bytes).err()
+        };
+
+        assert_eq!(RandomSequence::<u32>::decode(&bytes[..bytes.len() - 1]).err(), Some(StateError::Encoding));
+        assert_eq!(RandomSequence::<u64>::decode(&bytes).err(), Some(StateError::Encoding));
+        let mut version = bytes;
+        version[0] = 2;
+        assert_eq!(RandomSequence::<u32>::decode(&version).err(), Some(StateError::Version));
+        version[0] = 0;
+        assert_eq!(RandomSequence::<u32>::decode(&version).err(), Some(StateError::Version));
+        assert_eq!(RandomSequence::<u32>::decode(&[]).err(), Some(StateError::Encoding));
+        let mut flags = bytes;
+        flags[1] |= 1 << 7;
+        assert_eq!(RandomSequence::<u32>::decode(&flags).err(), Some(StateError::Encoding));
+        let mut unbounded = bytes;
+        unbounded[1] &= !FLAG_BOUNDED;
+        assert_eq!(RandomSequence::<u32>::decode(&unbounded).err(), Some(StateError::Encoding));
+
+        // the prime must be a 3 mod 4 prime within the domain of 1024
+        assert_eq!(with_word(4, 1009), Some(StateError::Prime));
+        assert_eq!(with_word(4, 1015), Some(StateError::Prime));
+        assert_eq!(with_word(4, 1031), Some(StateError::Prime));
+        assert_eq!(with_word(4, 1019), None);
+        assert_eq!(with_word(5, 1024), Some(StateError::Domain));
+        assert_eq!(with_word(6, 0), Some(StateError::Encoding));
+
+        // the cursors must be in order within the window, and the window within the sequence
+        assert_eq!(with_word(8, 1000), Some(StateError::Cursor));
+        assert_eq!(with_word(9, 1000), Some(StateError::Cursor));
+        assert_eq!(with_word(11, 1000), Some(StateError::Cursor));
+        assert_eq!(with_word(10, 2), Some(StateError::Cursor));
+        assert_eq!(with_word(8, 999), None);
+        assert_eq!(with_word(9, 5), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_config_version() {
+        let config = RandomSequenceBuilder::<u64>::new(5, 8).with_len(1000);
+        let mut value = serde_json::to_value(config).unwrap();
+        assert_eq!(value["version"], 1);
+        assert_eq!(serde_json::from_value::<RandomSequenceBuilder<u64>>(value.clone()).unwrap(), config);
+
+        // configs from before the version was recorded, without len and min, are unbounded version 1
+        let config = RandomSequenceBuilder::<u64>::new(5, 8);
+        let legacy = serde_json::json!({
+            "seed_base": 5,
+            "seed_offset": 8,
+            "init_base": config.init_base,
+            "init_offset": config.init_offset,
+            "prime": config.prime,
+            "intermediate_xor": config.intermediate_xor,
+        });
+        let legacy: RandomSequenceBuilder<u64> = serde_json::from_value(legacy).unwrap();
+        assert_eq!(legacy, config);
+
+        value["version"] = 2.into();
+        let error = serde_json::from_value::<RandomSequenceBuilder<u64>>(value).unwrap_err();
+        assert_eq!(error.to_string(), StateError::Version.to_string());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_config_invalid() {
+        // zero, even, and 1 mod 4 primes are rejected rather than building a broken sequence
+        let value = serde_json::to_value(RandomSequenceBuilder::<u64>::new(5, 8)).unwrap();
+        for prime in [0u64, 4, 1021, 18446744073709551557] {
+            let mut value = value.clone();
+            value["prime"] = prime.into();
+            let error = serde_json::from_value::<RandomSequenceBuilder<u64>>(value).unwrap_err();
+            assert_eq!(error.to_string(), StateError::Prime.to_string());
+        }
+
+        let value = serde_json::to_value(RandomSequenceBuilder::<u8>::new(5, 8)).unwrap();
+        for prime in [0, 2, 4, 13, 255] {
+            let mut value = value.clone();
+            value["prime"] = prime.into();
+            assert!(serde_json::from_value::<RandomSequenceBuilder<u8>>(value).is_err());
+        }
+
+        let mut value = serde_json::to_value(RandomSequenceBuilder::<u32>::new(5, 8)).unwrap();
+        value["len"] = 0.into();
+        let error = serde_json::from_value::<RandomSequenceBuilder<u32>>(value).unwrap_err();
+        assert_eq!(error.to_string(), StateError::Domain.to_string());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let mut sequence = RandomSequenceBuilder::<u64>::new(5, 8).with_len(1000).into_iter().shard(1, 2);
+        sequence.next();
+        let json = serde_json::to_string(&sequence).unwrap();
+        let decoded: RandomSequence<u64> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.index(), sequence.index());
+        assert!(decoded.eq(sequence.clone()));
+
+        // a state which is inconsistent with its config is rejected
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value["current_index"] = 1000.into();
+        assert!(serde_json::from_value::<RandomSequence<u64>>(value).is_err());
+
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value["permutation"]["intermediate_offset"] = 1.into();
+        assert!(serde_json::from_value::<RandomSequence<u64>>(value).is_err());
+
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value["config"]["prime"] = 1021.into();
+        assert!(serde_json::from_value::<RandomSequence<u64>>(value).is_err());
+    }
+}